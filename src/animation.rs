@@ -0,0 +1,228 @@
+use std::{collections::VecDeque, io::Cursor, sync::Arc, time::Duration};
+
+use image::{AnimationDecoder, DynamicImage, Frame, Frames, ImageFormat, ImageResult, codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder}};
+
+use crate::{image_loader::Cancellation, tiled_texture::TiledTexture};
+
+// Browsers treat tiny delays as a broken file and play them at 10fps, so do we
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const BROKEN_FRAME_DELAY: Duration = Duration::from_millis(100);
+// the video memory the frames of an animation may take, the frames of longer ones are uploaded while they play
+const FRAME_TEXTURE_BUDGET: usize = 256 * 1024 * 1024;
+
+pub const PLAYBACK_SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 1.5, 2.0, 4.0];
+
+#[derive(Clone)]
+pub struct AnimationFrame {
+    pub image: DynamicImage,
+    pub delay: Duration
+}
+
+/// Returns all the frames of an animated image, an empty vec is returned for still images.
+pub fn decode_frames(bytes: &[u8], format: ImageFormat, cancellation: &Cancellation) -> ImageResult<Vec<AnimationFrame>> {
+    let frames = match format {
+        ImageFormat::Gif => collect_frames(GifDecoder::new(Cursor::new(bytes))?.into_frames(), cancellation)?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng() {
+                return Ok(Vec::new());
+            }
+            collect_frames(decoder.apng().into_frames(), cancellation)?
+        },
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))?;
+            if !decoder.has_animation() {
+                return Ok(Vec::new());
+            }
+            collect_frames(decoder.into_frames(), cancellation)?
+        },
+        _ => return Ok(Vec::new())
+    };
+    if frames.len() < 2 {
+        return Ok(Vec::new());
+    }
+    Ok(frames.into_iter().map(|frame| {
+        let mut delay = Duration::from(frame.delay());
+        if delay < MIN_FRAME_DELAY {
+            delay = BROKEN_FRAME_DELAY;
+        }
+        AnimationFrame { image: DynamicImage::ImageRgba8(frame.into_buffer()), delay }
+    }).collect())
+}
+
+// long animations take a while, so the cancellation is checked between the frames
+fn collect_frames(frames: Frames, cancellation: &Cancellation) -> ImageResult<Vec<Frame>> {
+    frames.map(|frame| cancellation.check().and(frame)).collect()
+}
+
+// uploads a frame with its index
+type LoadFrame = dyn Fn(&DynamicImage, usize) -> TiledTexture;
+
+/// The textures of the frames of an animation, a frame is uploaded the first time it's shown.
+/// They are all kept if they fit in the budget, otherwise the oldest ones are dropped.
+pub struct FrameTextures {
+    frames: Arc<[AnimationFrame]>,
+    load: Box<LoadFrame>,
+    textures: Vec<Option<TiledTexture>>,
+    // the uploaded frames from the oldest to the newest
+    loaded: VecDeque<usize>,
+    capacity: usize
+}
+
+impl FrameTextures {
+    pub fn new(frames: Arc<[AnimationFrame]>, load: impl Fn(&DynamicImage, usize) -> TiledTexture + 'static) -> Self {
+        let frame_size = frames.first().map_or(0, |frame| frame.image.width() as usize * frame.image.height() as usize * 4);
+        let capacity = (FRAME_TEXTURE_BUDGET / frame_size.max(1)).max(2);
+        Self { textures: vec![None; frames.len()], frames, load: Box::new(load), loaded: VecDeque::new(), capacity }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Uploads the frame if it isn't already.
+    pub fn load(&mut self, index: usize) {
+        let Some(frame) = self.frames.get(index) else { return };
+        if self.textures[index].is_some() {
+            return;
+        }
+        if self.loaded.len() >= self.capacity {
+            if let Some(oldest) = self.loaded.pop_front() {
+                self.textures[oldest] = None;
+            }
+        }
+        self.textures[index] = Some((self.load)(&frame.image, index));
+        self.loaded.push_back(index);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&TiledTexture> {
+        self.textures.get(index)?.as_ref()
+    }
+}
+
+pub struct AnimationPlayer {
+    delays: Vec<Duration>,
+    current: usize,
+    // time spent on the current frame
+    elapsed: Duration,
+    pub playing: bool,
+    pub looping: bool,
+    pub speed: f32
+}
+
+impl AnimationPlayer {
+    pub fn new(delays: Vec<Duration>) -> Self {
+        assert!(!delays.is_empty(), "An animation must have at least one frame.");
+        Self {
+            delays,
+            current: 0,
+            elapsed: Duration::ZERO,
+            playing: true,
+            looping: true,
+            speed: 1.0
+        }
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.current
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.delays.len()
+    }
+
+    pub fn toggle_playback(&mut self) {
+        if !self.playing && !self.looping && self.current == self.delays.len() - 1 {
+            // replay from the start after the animation finished
+            self.current = 0;
+        }
+        self.playing = !self.playing;
+        self.elapsed = Duration::ZERO;
+    }
+
+    pub fn next_frame(&mut self) {
+        self.current = (self.current + 1) % self.delays.len();
+        self.elapsed = Duration::ZERO;
+    }
+
+    pub fn previous_frame(&mut self) {
+        self.current = (self.current + self.delays.len() - 1) % self.delays.len();
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Advances the animation by `delta_time` seconds, returns true if the frame changed.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        if !self.playing {
+            return false;
+        }
+        let previous = self.current;
+        self.elapsed += Duration::from_secs_f32(delta_time * self.speed);
+        while self.elapsed >= self.delays[self.current] {
+            self.elapsed -= self.delays[self.current];
+            if self.current == self.delays.len() - 1 && !self.looping {
+                self.playing = false;
+                self.elapsed = Duration::ZERO;
+                break;
+            }
+            self.current = (self.current + 1) % self.delays.len();
+        }
+        previous != self.current
+    }
+
+    pub fn time_until_next_frame(&self) -> Option<Duration> {
+        if self.playing {
+            let remaining = self.delays[self.current].saturating_sub(self.elapsed);
+            Some(remaining.div_f32(self.speed))
+        } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player() -> AnimationPlayer {
+        AnimationPlayer::new(vec![Duration::from_millis(100); 3])
+    }
+
+    #[test]
+    fn advances_frames() {
+        let mut player = player();
+        assert!(!player.update(0.05));
+        assert!(player.update(0.06));
+        assert_eq!(player.current_frame(), 1);
+        // skipping frames when a lot of time passed
+        assert!(player.update(0.2));
+        assert_eq!(player.current_frame(), 0);
+    }
+
+    #[test]
+    fn stops_without_looping() {
+        let mut player = player();
+        player.looping = false;
+        player.update(1.0);
+        assert_eq!(player.current_frame(), 2);
+        assert!(!player.playing);
+        player.toggle_playback();
+        assert_eq!(player.current_frame(), 0);
+        assert!(player.playing);
+    }
+
+    #[test]
+    fn speed() {
+        let mut player = player();
+        player.speed = 2.0;
+        assert!(player.update(0.06));
+        let remaining = player.time_until_next_frame().expect("The player is playing.");
+        assert!(remaining.abs_diff(Duration::from_millis(40)) < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn stepping() {
+        let mut player = player();
+        player.previous_frame();
+        assert_eq!(player.current_frame(), 2);
+        player.next_frame();
+        assert_eq!(player.current_frame(), 0);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use image::ImageFormat;
+
+use crate::{theme::ThemeKind, conversion::{convert_file, DEFAULT_OUTPUT_NAME_PATTERN}, image_directory::is_image_file};
+
+/// A fast image viewer.
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Images or directories to open, all the images in a directory are opened
+    pub paths: Vec<PathBuf>,
+    /// Start in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// How images are zoomed when they are opened: fit, a percentage like 100% or a scale like 2
+    #[arg(long, value_parser = parse_zoom)]
+    pub zoom: Option<Zoom>,
+    /// Go to the next image every SECONDS
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+    pub slideshow: Option<Duration>,
+    /// Show the slideshow in a random order
+    #[arg(long, requires = "slideshow")]
+    pub shuffle: bool,
+    /// The theme for this session
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemeKind>,
+    /// The texture filter for this session
+    #[arg(long, value_enum)]
+    pub filter: Option<Filter>,
+    /// The image to start at, the first image is 1
+    #[arg(long)]
+    pub index: Option<usize>,
+    /// Open the paths in the viewer that is already running, and accept control commands on a local socket
+    #[cfg(unix)]
+    #[arg(long)]
+    pub single_instance: bool
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Convert images to another format without opening a window
+    Convert(ConvertArgs)
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Images or glob patterns like "photos/*.png"
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    /// The format to convert to, by its extension like png or jpg
+    #[arg(short, long, value_parser = parse_output_format)]
+    pub format: OutputFormat,
+    /// The directory to write to, by default every image is written next to its source
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// The output file name, {stem} is the input name, {index} is the number of the input and {ext} is the extension
+    #[arg(short, long, default_value = DEFAULT_OUTPUT_NAME_PATTERN)]
+    pub name: String,
+    /// Replace files that already exist
+    #[arg(long)]
+    pub overwrite: bool
+}
+
+#[derive(Clone)]
+pub struct OutputFormat {
+    format: ImageFormat,
+    // the extension that was asked for, like jpg or jpeg
+    ext: String
+}
+
+impl ConvertArgs {
+    /// Converts every input and returns the exit code, 1 if any of them failed and 2 if nothing could be converted.
+    pub fn run(&self) -> i32 {
+        let inputs = match expand_inputs(&self.inputs) {
+            Ok(inputs) if inputs.is_empty() => {
+                eprintln!("No images matched the inputs.");
+                return 2;
+            },
+            Ok(inputs) => inputs,
+            Err(error) => {
+                eprintln!("{error}");
+                return 2;
+            }
+        };
+        if let Some(output) = self.output.as_ref() {
+            if let Err(error) = std::fs::create_dir_all(output) {
+                eprintln!("Couldn't create '{}'.\n{error}", output.display());
+                return 2;
+            }
+        }
+        let format = &self.format;
+        let mut failed = 0;
+        for (index, input) in inputs.iter().enumerate() {
+            match convert_file(input, self.output.as_deref(), &self.name, index + 1, format.format, &format.ext, self.overwrite) {
+                Ok(output) => println!("{} -> {}", input.display(), output.display()),
+                Err(error) => {
+                    eprintln!("{}: {error}", input.display());
+                    failed += 1;
+                }
+            }
+        }
+        if failed > 0 {
+            eprintln!("{failed} of {} images failed to convert.", inputs.len());
+            1
+        }
+        else { 0 }
+    }
+}
+
+/// Patterns are expanded here because windows shells don't expand them, a pattern that matches nothing is an error.
+fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if !input.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(input));
+            continue;
+        }
+        let matches = glob::glob(input).map_err(|error| format!("Invalid pattern '{input}'.\n{error}"))?;
+        let count = paths.len();
+        paths.extend(matches.filter_map(Result::ok).filter(|path| path.is_file() && is_image_file(path)));
+        if paths.len() == count {
+            return Err(format!("No images matched '{input}'."));
+        }
+    }
+    Ok(paths)
+}
+
+fn parse_output_format(ext: &str) -> Result<OutputFormat, String> {
+    let ext = ext.trim_start_matches('.').to_lowercase();
+    match ImageFormat::from_extension(&ext) {
+        Some(format) if format.can_write() => Ok(OutputFormat { format, ext }),
+        Some(_) => Err(format!("{ext} files can't be written")),
+        None => Err(format!("unknown format {ext}"))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Zoom {
+    Fit,
+    Scale(f32)
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Filter {
+    Nearest,
+    Linear
+}
+
+impl From<Filter> for egui::TextureFilter {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => egui::TextureFilter::Nearest,
+            Filter::Linear => egui::TextureFilter::Linear
+        }
+    }
+}
+
+/// The options that replace the settings until the app is closed, they are never stored.
+#[derive(Default)]
+pub struct SessionOverrides {
+    pub theme: Option<ThemeKind>,
+    pub image_filtering: Option<egui::TextureFilter>,
+    pub zoom: Option<Zoom>
+}
+
+impl Cli {
+    pub fn overrides(&self) -> SessionOverrides {
+        SessionOverrides {
+            theme: self.theme.clone(),
+            image_filtering: self.filter.map(egui::TextureFilter::from),
+            zoom: self.zoom
+        }
+    }
+}
+
+pub fn parse_zoom(value: &str) -> Result<Zoom, String> {
+    if value.eq_ignore_ascii_case("fit") {
+        return Ok(Zoom::Fit);
+    }
+    let scale = match value.strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f32>().map(|percentage| percentage / 100.0),
+        None => value.parse::<f32>()
+    };
+    match scale {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(Zoom::Scale(scale)),
+        _ => Err(String::from("expected fit, a percentage like 100% or a scale like 2"))
+    }
+}
+
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    value.parse::<f32>().ok()
+        .filter(|seconds| *seconds > 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f32(seconds).ok())
+        .ok_or_else(|| String::from("expected a positive number of seconds"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_zoom() {
+        assert_eq!(parse_zoom("fit"), Ok(Zoom::Fit));
+        assert_eq!(parse_zoom("100%"), Ok(Zoom::Scale(1.0)));
+        assert_eq!(parse_zoom("2.5"), Ok(Zoom::Scale(2.5)));
+        assert!(parse_zoom("0").is_err());
+        assert!(parse_zoom("big").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::{borrow::Cow, path::{Path, PathBuf}};
+
+use base64::Engine;
+use image::{DynamicImage, RgbaImage};
+
+use crate::image_directory::is_image_file;
+
+/// An image that was copied in another application.
+pub enum ClipboardImage {
+    Image(DynamicImage),
+    // the encoded file from a data uri, the format is sniffed when it's decoded
+    Bytes(Vec<u8>),
+    Path(PathBuf)
+}
+
+/// The system clipboard, it's opened the first time it's used.
+/// It's kept open because on linux the copied content is only available while the clipboard that copied it is alive.
+#[derive(Default)]
+pub struct SystemClipboard {
+    clipboard: Option<arboard::Clipboard>
+}
+
+impl SystemClipboard {
+    fn get(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new().map_err(|error| error.to_string())?);
+        }
+        Ok(self.clipboard.as_mut().expect("The clipboard was opened above."))
+    }
+
+    /// Reads the clipboard, bitmaps are preferred over text since browsers put both when copying an image.
+    pub fn paste_image(&mut self) -> Result<ClipboardImage, String> {
+        let clipboard = self.get()?;
+        if let Ok(data) = clipboard.get_image() {
+            let image = RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+                .ok_or_else(|| String::from("The clipboard image is smaller than its size."))?;
+            return Ok(ClipboardImage::Image(DynamicImage::ImageRgba8(image)));
+        }
+        let text = clipboard.get_text().map_err(|_| String::from("The clipboard doesn't contain an image."))?;
+        let text = text.trim();
+        if let Some(bytes) = parse_data_uri(text) {
+            return Ok(ClipboardImage::Bytes(bytes));
+        }
+        // file managers copy files as uris, one per line
+        let path = parse_path(text.lines().next().unwrap_or_default());
+        if path.is_file() && is_image_file(&path) {
+            Ok(ClipboardImage::Path(path))
+        }
+        else {
+            Err(String::from("The clipboard doesn't contain an image, a data uri or an image path."))
+        }
+    }
+
+    pub fn copy_image(&mut self, image: &RgbaImage) -> Result<(), String> {
+        let data = arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Borrowed(image.as_raw())
+        };
+        self.get()?.set_image(data).map_err(|error| error.to_string())
+    }
+
+    pub fn copy_text(&mut self, text: &str) -> Result<(), String> {
+        self.get()?.set_text(text).map_err(|error| error.to_string())
+    }
+
+    /// Copies the file itself, file managers paste a copy of it.
+    pub fn copy_file(&mut self, path: &Path) -> Result<(), String> {
+        self.get()?.set().file_list(&[path]).map_err(|error| error.to_string())
+    }
+}
+
+/// Returns the bytes of a base64 `data:image/...` uri.
+fn parse_data_uri(text: &str) -> Option<Vec<u8>> {
+    let (header, data) = text.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    if !mime_type.starts_with("image/") {
+        return None;
+    }
+    // uris copied from html or css are sometimes wrapped over multiple lines
+    let data: String = data.chars().filter(|char| !char.is_whitespace()).collect();
+    base64::engine::general_purpose::STANDARD.decode(data).ok()
+}
+
+/// Decodes `file://` uris, the spaces and other characters in them are percent encoded.
+fn parse_path(text: &str) -> PathBuf {
+    let Some(uri) = text.strip_prefix("file://") else { return PathBuf::from(text) };
+    let bytes: Vec<u8> = percent_encoding::percent_decode_str(uri).collect();
+    #[cfg(unix)]
+    return PathBuf::from(<std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(bytes));
+    #[cfg(not(unix))]
+    return PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_data_uris() {
+        assert_eq!(parse_data_uri("data:image/png;base64,iVBO\nRw=="), Some(vec![0x89, 0x50, 0x4e, 0x47]));
+        assert_eq!(parse_data_uri("data:text/plain;base64,iVBORw=="), None);
+        assert_eq!(parse_data_uri("data:image/svg+xml,<svg/>"), None);
+        assert_eq!(parse_data_uri("image.png"), None);
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(parse_path("file:///home/me/My%20Photos/caf%C3%A9.jpg"), PathBuf::from("/home/me/My Photos/café.jpg"));
+        assert_eq!(parse_path("/home/me/100%.png"), PathBuf::from("/home/me/100%.png"));
+    }
+}
@@ -1,20 +1,101 @@
+use image::{DynamicImage, GenericImageView};
 
 use crate::{widgets::{close_button, Button}, theme::Theme, settings::Settings, color_name::color_to_name, utilities::num_length};
 
+/// The channel values of a pixel in the type the image stores them in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Samples {
+    U8([u8; 4]),
+    U16([u16; 4]),
+    F32([f32; 4])
+}
+
+/// A pixel as it's stored in the image, before it's converted for display.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PixelSample {
+    // only the first `channels.len()` samples are used
+    pub samples: Samples,
+    // the names of the channels in the image, like "RGB" or "LA"
+    pub channels: &'static str,
+    // in stops, float samples are also shown with the exposure they were viewed with
+    pub exposure: f32
+}
+
+impl PixelSample {
+    pub fn read(image: &DynamicImage, x: u32, y: u32) -> Self {
+        fn pad<T: Copy + Default>(values: &[T]) -> [T; 4] {
+            let mut padded = [T::default(); 4];
+            padded[..values.len()].copy_from_slice(values);
+            padded
+        }
+        let (samples, channels) = match image {
+            DynamicImage::ImageLuma8(image) => (Samples::U8(pad(&image.get_pixel(x, y).0)), "L"),
+            DynamicImage::ImageLumaA8(image) => (Samples::U8(pad(&image.get_pixel(x, y).0)), "LA"),
+            DynamicImage::ImageRgb8(image) => (Samples::U8(pad(&image.get_pixel(x, y).0)), "RGB"),
+            DynamicImage::ImageRgba8(image) => (Samples::U8(image.get_pixel(x, y).0), "RGBA"),
+            DynamicImage::ImageLuma16(image) => (Samples::U16(pad(&image.get_pixel(x, y).0)), "L"),
+            DynamicImage::ImageLumaA16(image) => (Samples::U16(pad(&image.get_pixel(x, y).0)), "LA"),
+            DynamicImage::ImageRgb16(image) => (Samples::U16(pad(&image.get_pixel(x, y).0)), "RGB"),
+            DynamicImage::ImageRgba16(image) => (Samples::U16(image.get_pixel(x, y).0), "RGBA"),
+            DynamicImage::ImageRgb32F(image) => (Samples::F32(pad(&image.get_pixel(x, y).0)), "RGB"),
+            DynamicImage::ImageRgba32F(image) => (Samples::F32(image.get_pixel(x, y).0), "RGBA"),
+            other => (Samples::U8(other.get_pixel(x, y).0), "RGBA")
+        };
+        Self { samples, channels, exposure: 0.0 }
+    }
+
+    pub fn with_exposure(self, exposure: f32) -> Self {
+        Self { exposure, ..self }
+    }
+
+    /// Whether the samples hold more than the 8 bits that are shown for the color.
+    pub fn is_high_bit_depth(&self) -> bool {
+        !matches!(self.samples, Samples::U8(_))
+    }
+
+    pub fn bit_depth_name(&self) -> &'static str {
+        match self.samples {
+            Samples::U8(_) => "8-bit",
+            Samples::U16(_) => "16-bit",
+            Samples::F32(_) => "32-bit float"
+        }
+    }
+
+    pub fn format_samples(&self, exposure: f32) -> String {
+        let count = self.channels.len();
+        let values: Vec<String> = match self.samples {
+            Samples::U8(samples) => samples[..count].iter().map(u8::to_string).collect(),
+            Samples::U16(samples) => samples[..count].iter().map(u16::to_string).collect(),
+            Samples::F32(samples) => {
+                let scale = exposure.exp2();
+                // alpha isn't affected by the exposure
+                samples[..count].iter()
+                    .zip(self.channels.chars())
+                    .map(|(sample, channel)| format!("{:.4}", if channel == 'A' { *sample } else { sample * scale }))
+                    .collect()
+            }
+        };
+        values.join(" ")
+    }
+}
+
 pub struct ColorAnalyzerOpenState {
     pub picking_color: bool
 }
 
 pub struct ColorAnalyzer {
     pub open: Option<ColorAnalyzerOpenState>,
-    color: egui::Color32
+    color: egui::Color32,
+    // the picked pixel as it's stored in the image, only set if it's different from the shown color
+    source: Option<PixelSample>
 }
 
 impl ColorAnalyzer {
     pub fn new() -> Self {
         Self { 
             open: None, 
-            color: egui::Color32::TRANSPARENT
+            color: egui::Color32::TRANSPARENT,
+            source: None
         }
     }
 
@@ -44,8 +125,9 @@ impl ColorAnalyzer {
         self.open.as_ref().is_some_and(|opened_state| opened_state.picking_color)
     }
 
-    pub fn set_color(&mut self, color: egui::Color32) {
+    pub fn set_color(&mut self, color: egui::Color32, source: Option<PixelSample>) {
         self.color = color;
+        self.source = source;
     }
 }
 
@@ -71,11 +153,19 @@ impl ColorAnalyzer {
         }
 
         ui.spacing_mut().slider_width = ui.available_width();
+        let previous_color = self.color;
         egui::widgets::color_picker::color_picker_color32(ui, &mut self.color, egui::widgets::color_picker::Alpha::OnlyBlend);
         ui.add_space(5.0);
         color_name_display(ui, self.color);
         rgb_display(ui, &mut self.color);
         hex_display(ui, &mut self.color);
+        if let Some(source) = self.source.as_ref() {
+            source_display(ui, source);
+        }
+        // an edited color wasn't picked from the image, it has no source values
+        if self.color != previous_color {
+            self.source = None;
+        }
         
 
         ui.label(egui::RichText::new("Known Bugs").text_style(theme.heading3()));
@@ -116,7 +206,22 @@ fn rgb_display(ui: &mut egui::Ui, color: &mut egui::Color32) {
     });
 }
 
-
+fn source_display(ui: &mut egui::Ui, source: &PixelSample) {
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.label(format!("Source {}", source.channels));
+            ui.add_space(ui.spacing().item_spacing.x * 0.5);
+            ui.label(source.format_samples(0.0));
+        }).response.on_hover_text(format!("The {} value stored in the image, before it was converted for display.", source.bit_depth_name()));
+        if matches!(source.samples, Samples::F32(_)) && source.exposure != 0.0 {
+            ui.horizontal(|ui| {
+                ui.label(format!("{:+.1} EV", source.exposure));
+                ui.add_space(ui.spacing().item_spacing.x * 0.5);
+                ui.label(source.format_samples(source.exposure));
+            });
+        }
+    });
+}
 
 fn hex_display(ui: &mut egui::Ui, color: &mut egui::Color32) {
     // TODO: make the hex input better
@@ -198,6 +303,18 @@ fn calc_char_str_width(ui: &egui::Ui, chars: &[char], count: usize, text_style:
 mod tests {
     use super::*;
 
+    #[test]
+    fn high_bit_depth_samples() {
+        let image = image::DynamicImage::ImageLuma16(image::ImageBuffer::from_pixel(1, 1, image::Luma([40000u16])));
+        let sample = PixelSample::read(&image, 0, 0);
+        assert_eq!(sample.samples, Samples::U16([40000, 0, 0, 0]));
+        assert_eq!(sample.format_samples(0.0), "40000");
+        let image = image::DynamicImage::ImageRgba32F(image::ImageBuffer::from_pixel(1, 1, image::Rgba([1.5, 0.25, 0.0, 0.5])));
+        let sample = PixelSample::read(&image, 0, 0);
+        assert_eq!(sample.format_samples(0.0), "1.5000 0.2500 0.0000 0.5000");
+        assert_eq!(sample.format_samples(1.0), "3.0000 0.5000 0.0000 0.5000");
+    }
+
     #[test]
     fn try_parse_hex() {
         assert_eq!(try_parse("FFF"), Some((255, 255, 255)));
@@ -0,0 +1,181 @@
+use std::io::Cursor;
+
+use image::{ImageDecoder, ImageFormat, codecs::{jpeg::JpegDecoder, png::PngDecoder, tiff::TiffDecoder, webp::WebPDecoder}};
+
+// the tag table comes right after the fixed size header
+const HEADER_SIZE: usize = 128;
+const TAG_ENTRY_SIZE: usize = 12;
+
+/// An embedded icc profile, images with one are converted to srgb before they are shown.
+pub struct ColorProfile {
+    pub name: String,
+    // `None` if the profile is already srgb or it can't be converted from
+    transform: Option<Transform>
+}
+
+enum Transform {
+    Rgb(qcms::Transform),
+    // from gray and alpha pairs to rgba
+    Gray(qcms::Transform)
+}
+
+impl ColorProfile {
+    /// Reads the profile embedded in the file, `None` if there is no valid profile.
+    pub fn read(bytes: &[u8], format: ImageFormat) -> Option<Self> {
+        Self::from_icc(&read_icc_profile(bytes, format)?)
+    }
+
+    /// Only rgb and gray profiles are supported, the pixels are rgba in both cases.
+    pub fn from_icc(icc: &[u8]) -> Option<Self> {
+        let color_space = icc.get(16..20)?;
+        if color_space != b"RGB " && color_space != b"GRAY" {
+            return None;
+        }
+        let profile = qcms::Profile::new_from_slice(icc, false)?;
+        let transform = if profile.is_sRGB() {
+            None
+        }
+        else {
+            let mut srgb = qcms::Profile::new_sRGB();
+            srgb.precache_output_transform();
+            if color_space == b"GRAY" {
+                qcms::Transform::new_to(&profile, &srgb, qcms::DataType::GrayA8, qcms::DataType::RGBA8, qcms::Intent::Perceptual).map(Transform::Gray)
+            }
+            else {
+                qcms::Transform::new(&profile, &srgb, qcms::DataType::RGBA8, qcms::Intent::Perceptual).map(Transform::Rgb)
+            }
+        };
+        let name = profile_description(icc).unwrap_or_else(|| String::from("Unnamed profile"));
+        Some(Self { name, transform })
+    }
+
+    /// Whether the pixels are different in the source and the display color spaces.
+    pub fn converts(&self) -> bool {
+        self.transform.is_some()
+    }
+
+    /// Converts unmultiplied rgba pixels from the profile color space to srgb in place.
+    pub fn to_display(&self, rgba: &mut [u8]) {
+        match self.transform.as_ref() {
+            Some(Transform::Rgb(transform)) => transform.apply(rgba),
+            Some(Transform::Gray(transform)) => {
+                // gray images are decoded to rgba with the same value in every channel
+                let gray: Vec<u8> = rgba.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[3]]).collect();
+                transform.convert(&gray, rgba);
+            },
+            None => ()
+        }
+    }
+
+    pub fn to_display_color(&self, color: [u8; 4]) -> [u8; 4] {
+        let mut color = color;
+        self.to_display(&mut color);
+        color
+    }
+}
+
+fn read_icc_profile(bytes: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    let cursor = Cursor::new(bytes);
+    match format {
+        ImageFormat::Jpeg => JpegDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::Png => PngDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(cursor).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(cursor).ok()?.icc_profile(),
+        _ => None
+    }
+}
+
+/// The name of the profile from the 'desc' tag, version 2 profiles store it as ascii and version 4 as utf-16.
+fn profile_description(icc: &[u8]) -> Option<String> {
+    let tag_count = read_u32(icc, HEADER_SIZE)? as usize;
+    let (offset, size) = (0..tag_count)
+        .map(|index| HEADER_SIZE + 4 + index * TAG_ENTRY_SIZE)
+        .find(|entry| icc.get(*entry..*entry + 4) == Some(b"desc"))
+        .and_then(|entry| Some((read_u32(icc, entry + 4)? as usize, read_u32(icc, entry + 8)? as usize)))?;
+    let tag = icc.get(offset..offset.checked_add(size)?)?;
+    let description = match tag.get(0..4)? {
+        b"desc" => {
+            let length = read_u32(tag, 8)? as usize;
+            let text = tag.get(12..12usize.checked_add(length)?)?;
+            String::from_utf8_lossy(text).trim_end_matches('\0').to_owned()
+        },
+        b"mluc" => {
+            // the first record is used, it's usually english
+            let length = read_u32(tag, 20)? as usize;
+            let start = read_u32(tag, 24)? as usize;
+            let text = tag.get(start..start.checked_add(length)?)?;
+            let units: Vec<u16> = text.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+            String::from_utf16_lossy(&units).trim_end_matches('\0').to_owned()
+        },
+        _ => return None
+    };
+    Some(description).filter(|description| !description.is_empty())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_with_tag(tag: &[u8]) -> Vec<u8> {
+        let mut icc = vec![0; HEADER_SIZE];
+        icc.extend_from_slice(&1u32.to_be_bytes());
+        icc.extend_from_slice(b"desc");
+        icc.extend_from_slice(&((HEADER_SIZE + 4 + TAG_ENTRY_SIZE) as u32).to_be_bytes());
+        icc.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        icc.extend_from_slice(tag);
+        icc
+    }
+
+    #[test]
+    fn reads_description() {
+        let mut desc = b"desc\0\0\0\0".to_vec();
+        desc.extend_from_slice(&10u32.to_be_bytes());
+        desc.extend_from_slice(b"Adobe RGB\0");
+        assert_eq!(profile_description(&profile_with_tag(&desc)).as_deref(), Some("Adobe RGB"));
+
+        let text: Vec<u8> = "Display P3".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let mut mluc = b"mluc\0\0\0\0".to_vec();
+        mluc.extend_from_slice(&1u32.to_be_bytes());
+        mluc.extend_from_slice(&12u32.to_be_bytes());
+        mluc.extend_from_slice(b"enUS");
+        mluc.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        mluc.extend_from_slice(&28u32.to_be_bytes());
+        mluc.extend_from_slice(&text);
+        assert_eq!(profile_description(&profile_with_tag(&mluc)).as_deref(), Some("Display P3"));
+
+        assert_eq!(profile_description(&[0; 16]), None);
+    }
+
+    fn profile_with_color_space(color_space: &[u8; 4], tag: &[u8], tag_data: &[u8]) -> Vec<u8> {
+        let mut icc = profile_with_tag(tag_data);
+        icc[128 + 4..128 + 8].copy_from_slice(tag);
+        let size = icc.len() as u32;
+        icc[0..4].copy_from_slice(&size.to_be_bytes());
+        icc[12..16].copy_from_slice(b"mntr");
+        icc[16..20].copy_from_slice(color_space);
+        icc[20..24].copy_from_slice(b"XYZ ");
+        icc[36..40].copy_from_slice(b"acsp");
+        icc
+    }
+
+    #[test]
+    fn converts_gray_and_skips_cmyk() {
+        // a gamma 1.0 curve, the values get brighter in srgb
+        let mut curve = b"curv\0\0\0\0".to_vec();
+        curve.extend_from_slice(&1u32.to_be_bytes());
+        curve.extend_from_slice(&[1, 0, 0, 0]);
+        let profile = ColorProfile::from_icc(&profile_with_color_space(b"GRAY", b"kTRC", &curve)).unwrap();
+        assert!(profile.converts());
+        let color = profile.to_display_color([50, 50, 50, 128]);
+        assert!(color[0] > 50 && color[0] == color[1] && color[1] == color[2]);
+        assert_eq!(color[3], 128);
+
+        let cmyk = profile_with_color_space(b"CMYK", b"A2B0", &[0; 32]);
+        assert!(ColorProfile::from_icc(&cmyk).is_none());
+    }
+}
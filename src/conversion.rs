@@ -0,0 +1,155 @@
+use std::{path::{Path, PathBuf}, ops::RangeInclusive};
+
+use image::{DynamicImage, ImageFormat, ImageResult};
+
+use crate::{animation::AnimationFrame, image_loader::decode_file};
+
+pub const DEFAULT_FRAME_NAME_PATTERN: &str = "{stem}_{frame:04}.{ext}";
+pub const DEFAULT_OUTPUT_NAME_PATTERN: &str = "{stem}.{ext}";
+
+/// Saves the image in the given format, converting the pixel type when the encoder doesn't support it.
+pub fn save_image(image: &DynamicImage, path: &Path, format: ImageFormat) -> ImageResult<()> {
+    match format {
+        // the jpeg encoder fails on images with an alpha channel
+        ImageFormat::Jpeg if image.color().has_alpha() => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).save_with_format(path, format)
+        },
+        _ => image.save_with_format(path, format)
+    }
+}
+
+/// Applies the rotation and flipping of the view, `rotation` is in clockwise quarter turns and is applied before flipping.
+pub fn transform_image(image: &DynamicImage, rotation: usize, flip_horizontal: bool, flip_vertical: bool) -> DynamicImage {
+    let image = match rotation % 4 {
+        1 => image.rotate90(),
+        2 => image.rotate180(),
+        3 => image.rotate270(),
+        _ => image.clone()
+    };
+    let image = if flip_horizontal { image.fliph() } else { image };
+    if flip_vertical { image.flipv() } else { image }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrameSelection {
+    Current,
+    Range,
+    All
+}
+
+/// Writes every frame in `frames` (0 based) to `directory`, named using `pattern`.
+/// Returns how many frames were written.
+pub fn export_frames(frames: &[AnimationFrame], range: RangeInclusive<usize>, directory: &Path, pattern: &str, stem: &str, ext: &str, format: ImageFormat) -> Result<usize, String> {
+    let mut count = 0;
+    for index in range {
+        let frame = frames.get(index).ok_or_else(|| format!("Frame {} doesn't exist.", index + 1))?;
+        let name = format_frame_name(pattern, stem, index + 1, ext)?;
+        save_image(&frame.image, &directory.join(&name), format)
+            .map_err(|error| format!("Couldn't save '{name}'.\n{error}"))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decodes `input` like the viewer does and saves it to `directory`, the input's directory if it's `None`.
+/// Returns the path that was written.
+pub fn convert_file(input: &Path, directory: Option<&Path>, pattern: &str, index: usize, format: ImageFormat, ext: &str, overwrite: bool) -> Result<PathBuf, String> {
+    let stem = input.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let name = format_output_name(pattern, &stem, index, ext)?;
+    let directory = directory.or_else(|| input.parent()).unwrap_or(Path::new(""));
+    let output = directory.join(name);
+    if output == input {
+        return Err(String::from("The output would replace the input."));
+    }
+    if !overwrite && output.exists() {
+        return Err(format!("'{}' already exists.", output.display()));
+    }
+    let decoded = decode_file(input).map_err(|error| error.to_string())?;
+    save_image(&decoded.image, &output, format).map_err(|error| format!("Couldn't save '{}'.\n{error}", output.display()))?;
+    Ok(output)
+}
+
+/// Fills a naming pattern like `{stem}_{frame:04}.{ext}`.
+/// `{frame}` is the 1 based frame number and can be zero padded with `{frame:0N}`.
+pub fn format_frame_name(pattern: &str, stem: &str, frame: usize, ext: &str) -> Result<String, String> {
+    format_name(pattern, stem, ("frame", frame), ext)
+}
+
+/// Fills a naming pattern for batch conversion, `{index}` is the 1 based number of the file and is padded like `{frame}`.
+pub fn format_output_name(pattern: &str, stem: &str, index: usize, ext: &str) -> Result<String, String> {
+    format_name(pattern, stem, ("index", index), ext)
+}
+
+fn format_name(pattern: &str, stem: &str, (counter_key, counter): (&str, usize), ext: &str) -> Result<String, String> {
+    let mut name = String::new();
+    let mut chars = pattern.chars();
+    while let Some(char) = chars.next() {
+        if char != '{' {
+            name.push(char);
+            continue;
+        }
+        let mut placeholder = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(char) => placeholder.push(char),
+                None => return Err(format!("The placeholder '{{{placeholder}' is missing a closing '}}'."))
+            }
+        }
+        let (key, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+        match (key, spec) {
+            ("stem", "") => name.push_str(stem),
+            ("ext", "") => name.push_str(ext),
+            (key, "") if key == counter_key => name.push_str(&counter.to_string()),
+            (key, spec) if key == counter_key => {
+                let width = spec.strip_prefix('0').unwrap_or(spec).parse::<usize>()
+                    .map_err(|_| format!("Invalid {key} padding '{spec}'."))?;
+                if spec.starts_with('0') {
+                    name.push_str(&format!("{counter:0width$}"));
+                }
+                else {
+                    name.push_str(&format!("{counter:width$}"));
+                }
+            },
+            _ => return Err(format!("Unknown placeholder '{{{placeholder}}}'."))
+        }
+    }
+    if name.is_empty() {
+        return Err(String::from("The file name is empty."));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_names() {
+        assert_eq!(format_frame_name(DEFAULT_FRAME_NAME_PATTERN, "cat", 7, "png"), Ok(String::from("cat_0007.png")));
+        assert_eq!(format_frame_name("{frame}-{stem}.{ext}", "cat", 12, "gif"), Ok(String::from("12-cat.gif")));
+        assert_eq!(format_frame_name("{frame:02}", "cat", 123, "png"), Ok(String::from("123")));
+        assert_eq!(format_frame_name("{frame:3}", "cat", 5, "png"), Ok(String::from("  5")));
+        assert!(format_frame_name("{stem", "cat", 1, "png").is_err());
+        assert!(format_frame_name("{name}", "cat", 1, "png").is_err());
+        assert!(format_frame_name("{frame:x}", "cat", 1, "png").is_err());
+        assert!(format_frame_name("", "cat", 1, "png").is_err());
+    }
+
+    #[test]
+    fn output_names() {
+        assert_eq!(format_output_name(DEFAULT_OUTPUT_NAME_PATTERN, "cat", 3, "webp"), Ok(String::from("cat.webp")));
+        assert_eq!(format_output_name("{index:03}_{stem}.{ext}", "cat", 3, "png"), Ok(String::from("003_cat.png")));
+        assert!(format_output_name("{frame}.{ext}", "cat", 3, "png").is_err());
+    }
+
+    #[test]
+    fn transforms_images() {
+        let image = DynamicImage::ImageLuma8(image::GrayImage::from_raw(2, 1, vec![1, 2]).expect("The buffer fits the size."));
+        assert_eq!(transform_image(&image, 1, false, false).as_bytes(), [1, 2]);
+        assert_eq!(transform_image(&image, 1, false, false).height(), 2);
+        assert_eq!(transform_image(&image, 3, false, true).as_bytes(), [1, 2]);
+        assert_eq!(transform_image(&image, 2, true, false).as_bytes(), [1, 2]);
+        assert_eq!(transform_image(&image, 0, true, false).as_bytes(), [2, 1]);
+    }
+}
@@ -1,7 +1,8 @@
-use std::{path::PathBuf, fs};
+use std::sync::Arc;
 
-use egui::TextureHandle;
-use image::{ImageResult, EncodableLayout, DynamicImage};
+use image::{EncodableLayout, DynamicImage};
+
+use crate::{image_loader::DecodedImage, animation::{AnimationFrame, FrameTextures}, file_format::FileFormat, svg::SvgDocument, tiled_texture::{TiledImage, TiledTexture}, photo_metadata::PhotoMetadata, color_profile::ColorProfile, tone_mapping::ToneMapping, pages::Pages};
 
 
 
@@ -51,18 +52,25 @@ impl PainterEx for egui::Painter {
 }
 
 pub struct ImageLoadResult {
-    pub handle: egui::TextureHandle,
-    pub image: image::DynamicImage,
-    pub inner_format: Option<image::ImageFormat>,
-    pub file_format: Option<image::ImageFormat>
+    pub handle: TiledTexture,
+    // kept to upload the texture again when the texture options change
+    pub tiles: TiledImage,
+    pub image: Arc<DynamicImage>,
+    pub frames: Arc<[AnimationFrame]>,
+    pub frame_textures: FrameTextures,
+    pub pages: Option<Pages>,
+    pub inner_format: Option<FileFormat>,
+    pub file_format: Option<FileFormat>,
+    pub svg: Option<Arc<SvgDocument>>,
+    pub photo_metadata: Option<PhotoMetadata>,
+    pub color_profile: Option<Arc<ColorProfile>>
 }
 
 pub trait ContextEx {
     fn rect_contains_pointer(&self, layer_id: egui::LayerId, rect: egui::Rect) -> bool;
-    fn load_texture_file(&self, path: &PathBuf, options: egui::TextureOptions) -> ImageResult<ImageLoadResult>;
-    fn load_texture_raw(&self, path: &PathBuf, bytes: &[u8], options: egui::TextureOptions) -> ImageResult<ImageLoadResult>;
-    fn load_texture_raw_with_format_hint(&self, name: &str, bytes: &[u8], options: egui::TextureOptions, format_hint: ImageResult<image::ImageFormat>) -> ImageResult<ImageLoadResult>;        
-    fn load_texture_from_image(&self, image: &DynamicImage, options: egui::TextureOptions, name: impl Into<String>) -> TextureHandle;
+    fn load_texture_from_image(&self, image: &DynamicImage, color_profile: Option<&ColorProfile>, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: impl Into<String>) -> TiledTexture;
+    fn load_texture_from_decoded(&self, decoded: &DecodedImage, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: impl Into<String>) -> ImageLoadResult;
+    fn frame_textures(&self, frames: Arc<[AnimationFrame]>, color_profile: Option<Arc<ColorProfile>>, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: String) -> FrameTextures;
     fn delta_time(&self) -> f32;
 }
 
@@ -79,52 +87,40 @@ impl ContextEx for egui::Context {
         }
     }
 
-    fn load_texture_file(&self, path: &PathBuf, options: egui::TextureOptions) -> ImageResult<ImageLoadResult> {
-        let name = path.to_string_lossy();
-        let file_image_format = image::ImageFormat::from_path(path);
-        let bytes = fs::read(path).map_err(image::ImageError::IoError)?;
-        self.load_texture_raw_with_format_hint(&name,  &bytes, options, file_image_format)
+    fn load_texture_from_image(&self, image: &DynamicImage, color_profile: Option<&ColorProfile>, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: impl Into<String>) -> TiledTexture {
+        TiledTexture::load(self, color_image(image, color_profile, tone_mapping), options, name)
     }
 
-    fn load_texture_raw(&self, path: &PathBuf, bytes: &[u8], options: egui::TextureOptions) -> ImageResult<ImageLoadResult> {
-        let name = path.to_string_lossy();
-        let file_image_format = image::ImageFormat::from_path(path);
-        self.load_texture_raw_with_format_hint(&name, bytes, options, file_image_format)
-    }
-    fn load_texture_raw_with_format_hint(&self, name: &str, bytes: &[u8], options: egui::TextureOptions, format_hint: ImageResult<image::ImageFormat>) -> ImageResult<ImageLoadResult> {
-        let guessed_format = image::guess_format(bytes);
-        let inner_format = guessed_format.as_ref().ok().cloned();
-        let file_format_hint = format_hint.as_ref().ok().cloned();
-        let format = guessed_format.or(format_hint)?;
-        let image = image::load_from_memory_with_format(bytes, format)?;
-        let handle = self.load_texture_from_image(&image, options, name);
-        Ok(ImageLoadResult { 
+    fn load_texture_from_decoded(&self, decoded: &DecodedImage, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: impl Into<String>) -> ImageLoadResult {
+        let name = name.into();
+        // the loader prepared it unless the tone mapping changed since
+        let tiles = decoded.display_for(tone_mapping).cloned().unwrap_or_else(|| {
+            let max_side = self.input(|input| input.max_texture_side);
+            TiledImage::new(color_image(&decoded.image, decoded.color_profile.as_deref(), tone_mapping), max_side)
+        });
+        let handle = TiledTexture::upload(self, &tiles, options, name.clone());
+        let frame_textures = self.frame_textures(decoded.frames.clone(), decoded.color_profile.clone(), tone_mapping, options, name);
+        ImageLoadResult { 
             handle, 
-            image, 
-            inner_format, 
-            file_format: file_format_hint
-        })
+            tiles,
+            image: decoded.image.clone(), 
+            frames: decoded.frames.clone(),
+            frame_textures,
+            pages: decoded.pages.clone(),
+            inner_format: decoded.inner_format, 
+            file_format: decoded.file_format,
+            svg: decoded.svg.clone(),
+            photo_metadata: decoded.photo_metadata.clone(),
+            color_profile: decoded.color_profile.clone()
+        }
     }
 
-    fn load_texture_from_image(&self, image: &DynamicImage, options: egui::TextureOptions, name: impl Into<String>) -> TextureHandle {
-        let color_image = match &image {
-            DynamicImage::ImageRgb8(image) => {
-                // common case optimization
-                egui::ColorImage::from_rgb(
-                    [image.width() as usize, image.height() as usize],
-                    image.as_bytes(),
-                )
-            },
-            other => {
-                let image = other.to_rgba8();
-                egui::ColorImage::from_rgba_unmultiplied(
-                    [image.width() as usize, image.height() as usize],
-                    image.as_bytes(),
-                )
-            },
-        };
-        let handle = self.load_texture(name, color_image, options);
-        handle
+    fn frame_textures(&self, frames: Arc<[AnimationFrame]>, color_profile: Option<Arc<ColorProfile>>, tone_mapping: &ToneMapping, options: egui::TextureOptions, name: String) -> FrameTextures {
+        let context = self.clone();
+        let tone_mapping = *tone_mapping;
+        FrameTextures::new(frames, move |image, index| {
+            context.load_texture_from_image(image, color_profile.as_deref(), &tone_mapping, options, format!("{name}#{index}"))
+        })
     }
 
     fn delta_time(&self) -> f32 {
@@ -132,6 +128,38 @@ impl ContextEx for egui::Context {
     }
 }
 
+/// Converts the image to the colors shown on the screen, it's slow for big images so the loader does it on its worker.
+pub fn color_image(image: &DynamicImage, color_profile: Option<&ColorProfile>, tone_mapping: &ToneMapping) -> egui::ColorImage {
+    match image {
+        DynamicImage::ImageRgb8(image) if color_profile.is_none() => {
+            // common case optimization
+            egui::ColorImage::from_rgb(
+                [image.width() as usize, image.height() as usize],
+                image.as_bytes(),
+            )
+        },
+        other => {
+            let mut image = if ToneMapping::applies_to(other) {
+                // converting to rgba8 directly would clip everything above 1
+                match other.as_rgba32f() {
+                    Some(image) => tone_mapping.apply(image),
+                    None => tone_mapping.apply(&other.to_rgba32f())
+                }
+            }
+            else {
+                other.to_rgba8()
+            };
+            if let Some(color_profile) = color_profile {
+                color_profile.to_display(&mut image);
+            }
+            egui::ColorImage::from_rgba_unmultiplied(
+                [image.width() as usize, image.height() as usize],
+                image.as_bytes(),
+            )
+        },
+    }
+}
+
 pub trait Vec2Ex {
     fn rotate90_around(self, anchor: egui::Vec2, amount: usize) -> Self;
 }
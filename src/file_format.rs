@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use image::{ImageFormat, ImageResult};
+
+use crate::{image_directory::ImageFormatEx, raw::RawFormat};
+
+// how far into the file to look for the svg root element, the xml prolog and comments come before it
+const SVG_SNIFF_LENGTH: usize = 4096;
+// the brands of the ftyp box that mark heif files, avif has its own brand that the image crate knows
+const HEIF_BRANDS: [&[u8]; 8] = [b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1"];
+
+/// A file format the viewer can open, raster formats are handled by the image crate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileFormat {
+    Raster(ImageFormat),
+    Svg,
+    // decoded with libheif, only readable when the heif feature is enabled
+    Heif,
+    Raw(RawFormat)
+}
+
+impl FileFormat {
+    pub fn iterator() -> impl Iterator<Item = FileFormat> {
+        ImageFormat::iterator()
+            .map(|format| FileFormat::Raster(*format))
+            .chain([FileFormat::Svg, FileFormat::Heif])
+            .chain(RawFormat::iterator().map(FileFormat::Raw))
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> ImageResult<Self> {
+        let path = path.as_ref();
+        let ext = path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "svg" | "svgz" => Ok(FileFormat::Svg),
+            "heic" | "heif" => Ok(FileFormat::Heif),
+            _ => match RawFormat::from_extension(&ext) {
+                Some(format) => Ok(FileFormat::Raw(format)),
+                None => ImageFormat::from_path(path).map(FileFormat::Raster)
+            }
+        }
+    }
+
+    /// Guesses the format from the content of the file.
+    pub fn guess(bytes: &[u8]) -> ImageResult<Self> {
+        match image::guess_format(bytes) {
+            // raw files look like tiff files to the image crate
+            Ok(ImageFormat::Tiff) => Ok(RawFormat::detect(bytes).map_or(FileFormat::Raster(ImageFormat::Tiff), FileFormat::Raw)),
+            Ok(format) => Ok(FileFormat::Raster(format)),
+            Err(_) if is_svg(bytes) => Ok(FileFormat::Svg),
+            Err(_) if is_heif(bytes) => Ok(FileFormat::Heif),
+            Err(error) => Err(error)
+        }
+    }
+
+    pub fn can_read(&self) -> bool {
+        match self {
+            // image is built without its avif decoder, libheif decodes them
+            FileFormat::Raster(ImageFormat::Avif) | FileFormat::Heif => cfg!(feature = "heif"),
+            FileFormat::Raster(format) => format.can_read(),
+            FileFormat::Svg | FileFormat::Raw(_) => true
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            FileFormat::Raster(format) => format!("{format:?}").to_uppercase(),
+            FileFormat::Svg => String::from("SVG"),
+            FileFormat::Heif => String::from("HEIF"),
+            FileFormat::Raw(format) => format.name().to_owned()
+        }
+    }
+
+    pub fn extensions_str(&self) -> &'static [&'static str] {
+        match self {
+            FileFormat::Raster(format) => format.extensions_str(),
+            FileFormat::Svg => &["svg", "svgz"],
+            FileFormat::Heif => &["heic", "heif"],
+            FileFormat::Raw(format) => format.extensions_str()
+        }
+    }
+
+    /// The image crate format, `None` for formats that are decoded by other crates.
+    pub fn raster(&self) -> Option<ImageFormat> {
+        match self {
+            FileFormat::Raster(format) => Some(*format),
+            _ => None
+        }
+    }
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let start = &bytes[..bytes.len().min(SVG_SNIFF_LENGTH)];
+    String::from_utf8_lossy(start).contains("<svg")
+}
+
+fn is_heif(bytes: &[u8]) -> bool {
+    bytes.get(4..8) == Some(b"ftyp") && bytes.get(8..12).is_some_and(|brand| HEIF_BRANDS.contains(&brand))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_svg() {
+        assert_eq!(FileFormat::from_path(Path::new("icon.SVG")).ok(), Some(FileFormat::Svg));
+        assert_eq!(FileFormat::from_path(Path::new("icon.png")).ok(), Some(FileFormat::Raster(ImageFormat::Png)));
+        let svg = b"<?xml version=\"1.0\"?>\n<!-- logo -->\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+        assert_eq!(FileFormat::guess(svg).ok(), Some(FileFormat::Svg));
+        assert!(FileFormat::guess(b"<html></html>").is_err());
+        assert_eq!(FileFormat::guess(b"\0\0\0\x18ftypheic\0\0\0\0mif1heic").ok(), Some(FileFormat::Heif));
+        assert_eq!(FileFormat::Raster(ImageFormat::Avif).can_read(), cfg!(feature = "heif"));
+    }
+}
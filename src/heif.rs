@@ -0,0 +1,55 @@
+use image::{DynamicImage, ImageBuffer, ImageError, ImageResult, error::{DecodingError, ImageFormatHint}};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+use crate::color_profile::ColorProfile;
+
+/// Decodes heif and avif files with libheif, the rotation and mirroring stored in the file are already applied.
+pub fn decode(bytes: &[u8]) -> ImageResult<(DynamicImage, Option<ColorProfile>)> {
+    let context = HeifContext::read_from_bytes(bytes).map_err(decoding_error)?;
+    let handle = context.primary_image_handle().map_err(decoding_error)?;
+    let alpha = handle.has_alpha_channel();
+    // 10 and 12 bit images are kept in 16 bits so the extra precision isn't lost
+    let high_bit_depth = handle.luma_bits_per_pixel() > 8;
+    let chroma = match (high_bit_depth, alpha) {
+        (false, false) => RgbChroma::Rgb,
+        (false, true) => RgbChroma::Rgba,
+        (true, false) => RgbChroma::HdrRgbLe,
+        (true, true) => RgbChroma::HdrRgbaLe
+    };
+    let decoded = LibHeif::new().decode(&handle, ColorSpace::Rgb(chroma), None).map_err(decoding_error)?;
+    let plane = decoded.planes().interleaved.ok_or_else(|| decoding_error("The decoded image has no pixels."))?;
+    let channels = if alpha { 4 } else { 3 };
+    let sample_size = if high_bit_depth { 2 } else { 1 };
+    let row_size = plane.width as usize * channels * sample_size;
+    // the rows are padded to the stride
+    let rows = plane.data.chunks(plane.stride).take(plane.height as usize).map(|row| &row[..row_size]);
+    let image = if high_bit_depth {
+        let max = ((1u32 << plane.bits_per_pixel) - 1).max(1);
+        let samples: Vec<u16> = rows
+            .flat_map(|row| row.chunks_exact(2))
+            .map(|sample| (u16::from_le_bytes([sample[0], sample[1]]) as u32 * u16::MAX as u32 / max) as u16)
+            .collect();
+        if alpha {
+            ImageBuffer::from_raw(plane.width, plane.height, samples).map(DynamicImage::ImageRgba16)
+        }
+        else {
+            ImageBuffer::from_raw(plane.width, plane.height, samples).map(DynamicImage::ImageRgb16)
+        }
+    }
+    else {
+        let samples: Vec<u8> = rows.flatten().copied().collect();
+        if alpha {
+            ImageBuffer::from_raw(plane.width, plane.height, samples).map(DynamicImage::ImageRgba8)
+        }
+        else {
+            ImageBuffer::from_raw(plane.width, plane.height, samples).map(DynamicImage::ImageRgb8)
+        }
+    };
+    let image = image.ok_or_else(|| decoding_error("The decoded image is smaller than its size."))?;
+    let color_profile = handle.color_profile_raw().and_then(|profile| ColorProfile::from_icc(&profile.data));
+    Ok((image, color_profile))
+}
+
+fn decoding_error(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("HEIF")), error))
+}
@@ -0,0 +1,156 @@
+use std::{path::{Path, PathBuf}, fs, sync::Arc, time::SystemTime};
+
+use crate::image_loader::DecodedImage;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CacheKey {
+    path: PathBuf,
+    modified: SystemTime
+}
+
+impl CacheKey {
+    pub fn new(path: &Path, modified: SystemTime) -> Self {
+        Self { path: path.to_path_buf(), modified }
+    }
+
+    pub fn for_file(path: &Path) -> Option<Self> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        Some(Self::new(path, modified))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+struct CacheEntry {
+    key: CacheKey,
+    image: Arc<DecodedImage>,
+    size: usize
+}
+
+/// Least recently used cache of decoded images limited by the memory the pixels take.
+/// The images are shared with the opened image, getting one doesn't copy the pixels.
+pub struct ImageCache {
+    // ordered from the least recently used to the most recently used
+    entries: Vec<CacheEntry>,
+    budget: usize,
+    used: usize
+}
+
+impl ImageCache {
+    pub fn new(budget: usize) -> Self {
+        Self { entries: Vec::new(), budget, used: 0 }
+    }
+
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.iter().any(|entry| entry.key == *key)
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<&Arc<DecodedImage>> {
+        let index = self.entries.iter().position(|entry| entry.key == *key)?;
+        let entry = self.entries.remove(index);
+        self.entries.push(entry);
+        self.entries.last().map(|entry| &entry.image)
+    }
+
+    pub fn can_fit(&self, image: &DecodedImage) -> bool {
+        image.memory_size() <= self.budget
+    }
+
+    pub fn insert(&mut self, key: CacheKey, image: Arc<DecodedImage>) {
+        if !self.can_fit(&image) {
+            return;
+        }
+        let size = image.memory_size();
+        // a file was modified, the older versions will never be requested again
+        self.remove_path(key.path());
+        self.used += size;
+        self.entries.push(CacheEntry { key, image, size });
+        self.evict();
+    }
+
+    pub fn remove_path(&mut self, path: &Path) {
+        let used = &mut self.used;
+        self.entries.retain(|entry| {
+            let keep = entry.key.path != path;
+            if !keep {
+                *used -= entry.size;
+            }
+            keep
+        });
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.budget && !self.entries.is_empty() {
+            let entry = self.entries.remove(0);
+            self.used -= entry.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn key(name: &str) -> CacheKey {
+        CacheKey::new(Path::new(name), SystemTime::UNIX_EPOCH)
+    }
+
+    fn image(width: u32, height: u32) -> Arc<DecodedImage> {
+        Arc::new(DecodedImage {
+            image: Arc::new(image::DynamicImage::new_rgba8(width, height)),
+            frames: Arc::default(),
+            pages: None,
+            inner_format: None,
+            file_format: None,
+            svg: None,
+            photo_metadata: None,
+            color_profile: None,
+            display: None
+        })
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        // every 4x4 rgba image takes 64 bytes
+        let mut cache = ImageCache::new(64 * 3);
+        cache.insert(key("a"), image(4, 4));
+        cache.insert(key("b"), image(4, 4));
+        cache.insert(key("c"), image(4, 4));
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("d"), image(4, 4));
+        assert!(cache.contains(&key("a")));
+        assert!(!cache.contains(&key("b")));
+        assert!(cache.contains(&key("c")));
+        assert!(cache.contains(&key("d")));
+    }
+
+    #[test]
+    fn modified_file_replaces_old_entry() {
+        let mut cache = ImageCache::new(1024);
+        let modified = CacheKey::new(Path::new("a"), SystemTime::UNIX_EPOCH + Duration::from_secs(10));
+        cache.insert(key("a"), image(4, 4));
+        cache.insert(modified.clone(), image(4, 4));
+        assert!(!cache.contains(&key("a")));
+        assert!(cache.contains(&modified));
+        assert_eq!(cache.used, 64);
+    }
+
+    #[test]
+    fn budget() {
+        let mut cache = ImageCache::new(64);
+        cache.insert(key("big"), image(8, 8));
+        assert!(!cache.contains(&key("big")));
+        cache.insert(key("a"), image(4, 4));
+        cache.set_budget(0);
+        assert!(!cache.contains(&key("a")));
+        assert_eq!(cache.used, 0);
+    }
+}
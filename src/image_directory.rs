@@ -1,5 +1,6 @@
-use std::{path::{PathBuf, Path}, fs, sync::mpsc::{self, Receiver}, slice::Iter};
+use std::{path::{PathBuf, Path}, fs, sync::mpsc::{self, Receiver, Sender, TryRecvError}, slice::Iter, collections::{HashMap, HashSet}, thread};
 use image::ImageFormat;
+use crate::{file_format::FileFormat, sort_order::{SortKey, SortOrder, SortValues}, utilities::format_path, image_filter::ImageFilter};
 use notify::{Watcher, RecommendedWatcher, RecursiveMode, event::{CreateKind, Event, EventKind, RemoveKind, ModifyKind, RenameMode}};
 
 
@@ -8,26 +9,133 @@ pub struct ImageDirectory {
     name: String, // for cache
     index: usize,
     children: Vec<PathBuf>,
-    // for file system changes
+    // the paths it was opened with, they are browsed again when the subfolders are included or excluded
+    paths: Vec<PathBuf>,
+    // new images in these directories are added to the children
+    directories: Vec<PathBuf>,
+    sort_order: SortOrder,
+    // read once per file, the reading is slow for the dates, sizes and dimensions
+    sort_values: SortValues,
+    include_subfolders: bool,
+    filter: ImageFilter,
+    // the children that don't match the filter, they are skipped when browsing
+    excluded: HashSet<PathBuf>,
+    // read from the file headers on a worker for the size limits of the filter
+    dimensions: HashMap<PathBuf, Option<(u32, u32)>>,
+    // the paths whose dimensions are being read
+    reading_dimensions: HashSet<PathBuf>,
+    // for file system changes and the results of the workers
     receiver: Receiver<FsChange>,
-    _watcher: RecommendedWatcher
+    sender: Sender<FsChange>,
+    _watcher: RecommendedWatcher,
+    // walks and watches the subfolders when they are included
+    _subfolders: Option<SubfolderWorker>
 }
 
 impl ImageDirectory {
-    pub fn new(image_path: &PathBuf) -> Result<Self, notify::Error> {
+    pub fn new(image_path: &PathBuf, sort_order: SortOrder, include_subfolders: bool) -> Result<Self, notify::Error> {
         let parent = image_path.parent().expect("There isn't a parent path for the image path");
-        let image_entries = find_image_files(parent);
-        let current_index = image_entries.iter().position(|path| path == image_path).expect("Couldn't find the current image.");
-        let name = image_path.file_name().expect("Couldn't extract the file name").to_string_lossy().to_string();
-        let (mut watcher, receiver) = setup_watcher()?;
-        watcher.watch(parent, RecursiveMode::NonRecursive)?;
-        Ok(Self {
-            name,
-            index: current_index,
-            children: image_entries,
-            receiver: receiver,
-            _watcher: watcher
-        })
+        let mut directory = Self::browse(vec![parent.to_path_buf()], sort_order, include_subfolders)?;
+        let current_index = directory.children.iter().position(|path| path == image_path).expect("Couldn't find the current image.");
+        directory.go_to(current_index);
+        Ok(directory)
+    }
+
+    /// Browses the given images, directories are replaced by the images in them.
+    /// The paths should be absolute, `None` is returned if there are no images.
+    pub fn from_paths(paths: &[PathBuf], sort_order: SortOrder, include_subfolders: bool) -> Result<Option<Self>, notify::Error> {
+        let mut directory = Self::browse(paths.to_vec(), sort_order, include_subfolders)?;
+        if directory.children.is_empty() {
+            return Ok(None);
+        }
+        directory.go_to(0);
+        Ok(Some(directory))
+    }
+
+    // the current image isn't set, there might not be any images
+    fn browse(paths: Vec<PathBuf>, sort_order: SortOrder, include_subfolders: bool) -> Result<Self, notify::Error> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = setup_watcher(sender.clone())?;
+        let mut children = Vec::new();
+        let mut directories = Vec::new();
+        let mut watched = Vec::new();
+        for path in &paths {
+            let directory = if path.is_dir() {
+                add_new(&mut children, find_image_files(path, false));
+                directories.push(path.clone());
+                // the worker watches it together with its subfolders
+                if include_subfolders { continue; }
+                path.as_path()
+            }
+            else if is_image_file(path) && path.is_file() && !children.contains(path) {
+                children.push(path.clone());
+                path.parent().expect("There isn't a parent path for the image path")
+            }
+            else { continue };
+            // the parent of a listed file is watched so its renames and removals are seen
+            if !watched.iter().any(|watched| watched == directory) {
+                if let Err(error) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+                    let _ = sender.send(FsChange::WatchError(watch_error(directory, error)));
+                }
+                watched.push(directory.to_path_buf());
+            }
+        }
+        // big trees are walked on a worker, unless there is nothing else to show
+        let walked = include_subfolders && children.is_empty();
+        if walked {
+            for directory in &directories {
+                add_new(&mut children, find_image_files(directory, true));
+            }
+        }
+        let subfolders = (include_subfolders && !directories.is_empty())
+            .then(|| SubfolderWorker::start(directories.clone(), !walked, sort_order.key, sender.clone()));
+        // sorted by the name until the values are read
+        let sort_values = SortValues::default();
+        sort_order.sort(&mut children, &directories, &sort_values);
+        let mut directory = Self {
+            name: String::new(),
+            index: 0,
+            children,
+            paths,
+            directories,
+            sort_order,
+            sort_values,
+            include_subfolders,
+            filter: ImageFilter::default(),
+            excluded: HashSet::new(),
+            dimensions: HashMap::new(),
+            reading_dimensions: HashSet::new(),
+            receiver,
+            sender,
+            _watcher: watcher,
+            _subfolders: subfolders
+        };
+        directory.read_sort_values();
+        Ok(directory)
+    }
+
+    /// Browses the images in the subfolders too or stops browsing them.
+    /// Returns true if the current image is gone and another one should be loaded.
+    pub fn set_include_subfolders(&mut self, include_subfolders: bool) -> Result<bool, String> {
+        let mut directory = Self::browse(self.paths.clone(), self.sort_order, include_subfolders).map_err(|error| error.to_string())?;
+        if directory.children.is_empty() {
+            return Err(String::from("There are no images outside of the subfolders."));
+        }
+        directory.set_filter(self.filter.clone());
+        let current = directory.children.iter().position(|path| path == self.current_image_path());
+        directory.go_to(current.unwrap_or(0));
+        *self = directory;
+        Ok(current.is_none())
+    }
+
+    // images in the subfolders of the browsed directories are only added when they are included
+    fn is_browsed(&self, path: &Path) -> bool {
+        if self.include_subfolders {
+            self.directories.iter().any(|directory| path.starts_with(directory))
+        }
+        else {
+            path.parent().is_some_and(|parent| self.directories.iter().any(|directory| directory == parent))
+        }
     }
 
     pub fn image_name(&self) -> &String {
@@ -45,41 +153,206 @@ impl ImageDirectory {
         self.name.rsplit_once(".").and_then(|(_name, ext)| Some(ext))
     }
 
+    /// The name of the image, or its path from the browsed directory when the subfolders are included.
+    pub fn image_label(&self) -> String {
+        let current = self.current_image_path();
+        let relative = self.directories.iter().find_map(|directory| current.strip_prefix(directory).ok());
+        match relative {
+            Some(relative) if self.include_subfolders => format_path(relative),
+            _ => self.name.clone()
+        }
+    }
+
     pub fn image_index(&self) -> usize {
         self.index
     }
 
+    /// The place of the current image among the images that match the filter, `None` if it doesn't match.
+    pub fn filtered_index(&self) -> Option<usize> {
+        if self.excluded.contains(self.current_image_path()) {
+            return None;
+        }
+        Some(self.children[..self.index].iter().filter(|path| !self.excluded.contains(*path)).count())
+    }
+
     pub fn current_image_path(&self) -> &PathBuf {
         &self.children[self.index]
     }
 
+    pub fn path(&self, index: usize) -> &PathBuf {
+        &self.children[index]
+    }
+
     pub fn current_directory_path(&self) -> PathBuf {
         self.children[self.index].parent().expect("The parent path should exist.").to_path_buf()
     }
 
+    /// How many images match the filter.
     pub fn count(&self) -> usize {
-        self.children.len()
+        self.children.len() - self.excluded.len()
     }
 
-    pub fn next_image(&mut self) -> &PathBuf {
-        if self.index == self.children.len() - 1 {
-            self.index = 0;
+    /// The images that match the filter with their index for `go_to`.
+    pub fn filtered_images(&self) -> impl Iterator<Item = (usize, &PathBuf)> {
+        self.children.iter().enumerate().filter(|(_, path)| !self.excluded.contains(*path))
+    }
+
+    /// The paths of the images around the current one that match the filter, the closest ones first.
+    pub fn neighbours(&self, count: usize) -> Vec<PathBuf> {
+        let mut neighbours = Vec::new();
+        let mut next = self.index;
+        let mut previous = self.index;
+        for _ in 0..count.min(self.count()) {
+            next = self.step(next, true);
+            previous = self.step(previous, false);
+            for index in [next, previous] {
+                if index != self.index && !neighbours.contains(&self.children[index]) {
+                    neighbours.push(self.children[index].clone());
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Skips the images that don't match the filter, the current image is kept even if it doesn't match.
+    /// The dimensions for the size limits are read on a worker, the images pass the limits until they're there.
+    pub fn set_filter(&mut self, filter: ImageFilter) {
+        self.filter = filter;
+        self.update_filters();
+        self.read_dimensions();
+    }
+
+    fn update_filters(&mut self) {
+        self.excluded.clear();
+        for index in 0..self.children.len() {
+            self.update_filter(&self.children[index].clone());
+        }
+    }
+
+    fn update_filter(&mut self, path: &PathBuf) {
+        let matches = self.filter.matches(path, || self.dimensions.get(path).copied().flatten());
+        if matches {
+            self.excluded.remove(path);
         }
         else {
+            self.excluded.insert(path.clone());
+        }
+    }
+
+    // reads the dimensions that the filter needs and that weren't read yet on a worker
+    fn read_dimensions(&mut self) {
+        if !self.filter.needs_dimensions() {
+            return;
+        }
+        let missing: Vec<PathBuf> = self.children.iter()
+            .filter(|path| !self.dimensions.contains_key(*path) && !self.reading_dimensions.contains(*path))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.reading_dimensions.extend(missing.iter().cloned());
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            // only the header is read
+            let dimensions = missing.into_iter()
+                .map(|path| {
+                    let dimensions = image::image_dimensions(&path).ok();
+                    (path, dimensions)
+                })
+                .collect();
+            let _ = sender.send(FsChange::Dimensions(dimensions));
+        });
+    }
+
+    // the index of the next or previous image that matches the filter, it wraps around
+    fn step(&self, from: usize, forward: bool) -> usize {
+        let len = self.children.len();
+        let mut index = from;
+        for _ in 0..len {
+            index = if forward { (index + 1) % len } else { (index + len - 1) % len };
+            if !self.excluded.contains(&self.children[index]) {
+                return index;
+            }
+        }
+        // nothing else matches
+        from
+    }
+
+    /// Sorts the images again, the current image stays the same.
+    /// The values that weren't read yet are read on a worker, the images are sorted once they're there.
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sort_order = sort_order;
+        if !self.read_sort_values() {
+            self.sort();
+        }
+    }
+
+    // reads the values of the images that weren't read yet on a worker, returns false if there are none
+    fn read_sort_values(&mut self) -> bool {
+        let key = self.sort_order.key;
+        let missing = self.sort_values.request(key, &self.children);
+        if missing.is_empty() {
+            return false;
+        }
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send(FsChange::SortValues(SortValues::read(key, &missing)));
+        });
+        true
+    }
+
+    fn sort(&mut self) {
+        let current = self.current_image_path().clone();
+        self.sort_order.sort(&mut self.children, &self.directories, &self.sort_values);
+        self.index = self.children.iter().position(|path| *path == current).expect("The current image is still in the children.");
+    }
+
+    // inserts the path at its sorted position and keeps the current image, it's moved again once its value is read
+    fn insert_sorted(&mut self, path: PathBuf) {
+        let index = self.sort_order.position(&self.children, &path, &self.directories, &self.sort_values);
+        if index <= self.index && !self.children.is_empty() {
             self.index += 1;
         }
+        self.children.insert(index, path);
+        self.read_sort_values();
+    }
+
+    // moves a child whose name or file changed to its new place
+    fn reposition(&mut self, index: usize) {
+        let current = index == self.index;
+        let path = self.children.remove(index);
+        if index < self.index {
+            self.index -= 1;
+        }
+        let new_index = self.sort_order.position(&self.children, &path, &self.directories, &self.sort_values);
+        if current {
+            self.index = new_index;
+        }
+        else if new_index <= self.index {
+            self.index += 1;
+        }
+        self.children.insert(new_index, path);
+        self.read_sort_values();
+    }
+
+    /// Jumps to the image at `index`, the last image is used if it's out of range.
+    pub fn go_to(&mut self, index: usize) -> &PathBuf {
+        self.index = index.min(self.children.len() - 1);
+        let path = &self.children[self.index];
+        self.name = path.file_name().expect("Couldn't extract the file name").to_string_lossy().to_string(); 
+        path
+    }
+
+    pub fn next_image(&mut self) -> &PathBuf {
+        self.index = self.step(self.index, true);
         let path = &self.children[self.index];
         self.name = path.file_name().expect("Couldn't extract the file name").to_string_lossy().to_string(); 
         path
     }
 
     pub fn previous_image(&mut self) -> &PathBuf {
-        if self.index == 0 {
-            self.index = self.children.len() - 1;
-        }
-        else {
-            self.index -= 1;
-        }
+        self.index = self.step(self.index, false);
         let path = &self.children[self.index];
         self.name = path.file_name().expect("Couldn't extract the file name").to_string_lossy().to_string(); 
         path
@@ -98,8 +371,11 @@ impl ImageDirectory {
                         } else { None }
                     }
                     else {
-                        // TODO(maybe): sort the children and then insert it at the right place
-                        self.children.push(path);
+                        if self.is_browsed(&path) {
+                            self.update_filter(&path);
+                            self.insert_sorted(path);
+                            self.read_dimensions();
+                        }
                         None
                     }
                 },
@@ -107,11 +383,21 @@ impl ImageDirectory {
                     let index = self.children.iter().position(|child_path| *child_path == path);
                     if let Some(index) = index {
                         self.children.remove(index);
-                        let reload = index == self.index;
-                        if index <= self.index {
+                        self.sort_values.remove(&path);
+                        self.excluded.remove(&path);
+                        self.dimensions.remove(&path);
+                        self.reading_dimensions.remove(&path);
+                        if self.children.is_empty() {
+                            return Some(Change::Empty);
+                        }
+                        if index < self.index {
                             self.index -= 1;
+                            None
                         }
-                        if reload {
+                        else if index == self.index {
+                            // the next image that matches the filter takes its place, it wraps around to the first one
+                            let previous = (index + self.children.len() - 1) % self.children.len();
+                            self.go_to(self.step(previous, true));
                             Some(Change::NewImage)
                         } else { None }
                     }
@@ -121,11 +407,17 @@ impl ImageDirectory {
                     }
                 },
                 FsChange::FileChange(path) => {
-                    self.children
-                        .iter()
-                        .position(|child_path| *child_path == path)
-                        .and_then(|index| if index == self.index { Some(Change::NewImage) } else { None })
-                        
+                    let index = self.children.iter().position(|child_path| *child_path == path)?;
+                    let reload = index == self.index;
+                    // the dimensions might have changed too
+                    self.dimensions.remove(&path);
+                    self.reading_dimensions.remove(&path);
+                    self.sort_values.remove(&path);
+                    self.update_filter(&path);
+                    self.read_dimensions();
+                    // the size and dates change when the file is written
+                    self.reposition(index);
+                    if reload { Some(Change::NewImage) } else { None }
                 },
                 FsChange::Rename(from, to) => {
                     let index = self.children.iter().position(|child_path| *child_path == from);
@@ -134,7 +426,15 @@ impl ImageDirectory {
                             self.name = to.file_name().expect("Couldn't extract the file name").to_string_lossy().to_string();
                             Some(Change::Rename)
                         } else { None };
+                        self.excluded.remove(&from);
+                        self.sort_values.remove(&from);
+                        if let Some(dimensions) = self.dimensions.remove(&from) {
+                            self.dimensions.insert(to.clone(), dimensions);
+                        }
+                        self.update_filter(&to);
                         self.children[index] = to;
+                        self.read_dimensions();
+                        self.reposition(index);
                         res
                     }
                     else {
@@ -142,6 +442,37 @@ impl ImageDirectory {
                         None
                     }
                 },
+                FsChange::Found(paths, sort_values) => {
+                    let known: HashSet<&PathBuf> = self.children.iter().collect();
+                    let found: Vec<PathBuf> = paths.into_iter().filter(|path| !known.contains(path)).collect();
+                    for path in &found {
+                        self.update_filter(path);
+                    }
+                    self.children.extend(found);
+                    self.read_dimensions();
+                    // the sort order might have changed while they were read
+                    self.sort_values.extend(sort_values);
+                    self.read_sort_values();
+                    self.sort();
+                    None
+                },
+                FsChange::SortValues(sort_values) => {
+                    // the sort order changed again while they were read otherwise
+                    if self.sort_values.extend(sort_values) {
+                        self.sort();
+                    }
+                    None
+                },
+                FsChange::Dimensions(dimensions) => {
+                    for path in dimensions.keys() {
+                        self.reading_dimensions.remove(path);
+                    }
+                    self.dimensions.extend(dimensions);
+                    // the images that don't fit the size limits are excluded now
+                    self.update_filters();
+                    None
+                },
+                FsChange::WatchError(message) => Some(Change::WatchError(message)),
             }
         }
         else { None }
@@ -150,22 +481,99 @@ impl ImageDirectory {
 
 pub enum Change {
     NewImage,
-    Rename
+    Rename,
+    // the last image was removed, the directory should be closed
+    Empty,
+    // the directory is still browsed, but changes to it might be missed
+    WatchError(String)
 }
 
-fn find_image_files(path: &Path) -> Vec<PathBuf> {
-    fs::read_dir(path).expect("Couldn't read the parent dir")
-        .filter_map(|element| {
-            element.ok().and_then(|entry| {
-                if is_image_file(&entry.path()) {
-                    Some(entry.path())
+// directories that can't be read have no images
+fn find_image_files(path: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut images = Vec::new();
+    if let Ok(entries) = fs::read_dir(path) {
+        collect_image_files(entries, recursive, &mut images, &|| false);
+    }
+    images
+}
+
+fn collect_image_files(entries: fs::ReadDir, recursive: bool, images: &mut Vec<PathBuf>, cancelled: &dyn Fn() -> bool) {
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // the file type doesn't follow symbolic links, so a link to a parent directory can't loop forever
+        if recursive && entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            if cancelled() {
+                return;
+            }
+            // subfolders that can't be read are skipped
+            if let Ok(entries) = fs::read_dir(&path) {
+                collect_image_files(entries, recursive, images, cancelled);
+            }
+        }
+        else if is_image_file(&path) {
+            images.push(path);
+        }
+    }
+}
+
+fn add_new(children: &mut Vec<PathBuf>, images: Vec<PathBuf>) {
+    for image in images {
+        if !children.contains(&image) {
+            children.push(image);
+        }
+    }
+}
+
+fn watch_error(directory: &Path, error: notify::Error) -> String {
+    format!("Changes in '{}' won't be shown.\n{error}", format_path(directory))
+}
+
+/// Finds the images in the subfolders and watches them on a thread, walking a big tree would freeze the window.
+/// The thread stops when the directory is dropped.
+struct SubfolderWorker {
+    _stop: Sender<()>
+}
+
+impl SubfolderWorker {
+    fn start(directories: Vec<PathBuf>, walk: bool, sort_key: SortKey, sender: Sender<FsChange>) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            let cancelled = || matches!(stopped.try_recv(), Err(TryRecvError::Disconnected));
+            if walk {
+                let mut images = Vec::new();
+                for directory in &directories {
+                    let subfolders = fs::read_dir(directory).into_iter().flatten().flatten()
+                        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()));
+                    for subfolder in subfolders {
+                        if let Ok(entries) = fs::read_dir(subfolder.path()) {
+                            collect_image_files(entries, true, &mut images, &cancelled);
+                        }
+                    }
                 }
-                else {
-                    None
+                let sort_values = SortValues::read(sort_key, &images);
+                if cancelled() || sender.send(FsChange::Found(images, sort_values)).is_err() {
+                    return;
                 }
-            })
-        })
-        .collect::<Vec<PathBuf>>()
+            }
+            let mut watcher = match setup_watcher(sender.clone()) {
+                Ok(watcher) => watcher,
+                Err(error) => {
+                    let _ = sender.send(FsChange::WatchError(format!("Changes in the subfolders won't be shown.\n{error}")));
+                    return;
+                }
+            };
+            for directory in &directories {
+                if let Err(error) = watcher.watch(directory, RecursiveMode::Recursive) {
+                    // running out of inotify watches is common with big trees
+                    let _ = sender.send(FsChange::WatchError(watch_error(directory, error)));
+                    let _ = watcher.watch(directory, RecursiveMode::NonRecursive);
+                }
+            }
+            // keeps the watcher until the directory is dropped
+            let _ = stopped.recv();
+        });
+        Self { _stop: stop }
+    }
 }
 
 pub trait ImageFormatEx {
@@ -199,13 +607,18 @@ enum FsChange {
     Create(PathBuf),
     Remove(PathBuf),
     FileChange(PathBuf),
-    Rename(PathBuf, PathBuf)
+    Rename(PathBuf, PathBuf),
+    // the images in the subfolders
+    Found(Vec<PathBuf>, SortValues),
+    SortValues(SortValues),
+    // the dimensions of the images for the filter
+    Dimensions(HashMap<PathBuf, Option<(u32, u32)>>),
+    WatchError(String)
 }
 
-fn setup_watcher() -> notify::Result<(RecommendedWatcher, Receiver<FsChange>)> {
-    let (sender, receiver) = mpsc::channel();
+fn setup_watcher(sender: Sender<FsChange>) -> notify::Result<RecommendedWatcher> {
     let mut last_rename = None;
-    Ok((notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+    notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
         if let Ok(mut event) = res {
             match event.kind {
                 EventKind::Any => {}
@@ -279,9 +692,38 @@ fn setup_watcher() -> notify::Result<(RecommendedWatcher, Receiver<FsChange>)> {
                 EventKind::Other =>  {},
             }
         }
-    })?, receiver))
+    })
 }
 
 pub fn is_image_file(path: &PathBuf) -> bool {
-    image::ImageFormat::from_path(path).is_ok_and(|format| format.can_read())
-}
\ No newline at end of file
+    FileFormat::from_path(path).is_ok_and(|format| format.can_read())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_filter::NamePattern;
+
+    #[test]
+    fn removing_the_current_image() {
+        let directory_path = std::env::temp_dir().join(format!("enchanted-view-directory-{}", std::process::id()));
+        fs::create_dir_all(&directory_path).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(directory_path.join(name), b"").unwrap();
+        }
+        let mut directory = ImageDirectory::from_paths(std::slice::from_ref(&directory_path), SortOrder::default(), false).unwrap().unwrap();
+        directory.set_filter(ImageFilter { name: NamePattern::parse("[!b]*", false).ok(), ..Default::default() });
+
+        // the image after the first one doesn't match the filter
+        directory.sender.send(FsChange::Remove(directory_path.join("a.png"))).unwrap();
+        assert!(matches!(directory.check_for_changes(), Some(Change::NewImage)));
+        assert_eq!(directory.current_image_path(), &directory_path.join("c.png"));
+        assert_eq!(directory.image_name(), "c.png");
+        // nothing else matches
+        directory.sender.send(FsChange::Remove(directory_path.join("c.png"))).unwrap();
+        assert!(matches!(directory.check_for_changes(), Some(Change::NewImage)));
+        assert_eq!(directory.current_image_path(), &directory_path.join("b.png"));
+        directory.sender.send(FsChange::Remove(directory_path.join("b.png"))).unwrap();
+        assert!(matches!(directory.check_for_changes(), Some(Change::Empty)));
+        fs::remove_dir_all(&directory_path).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use crate::{file_format::FileFormat, widgets::{Button, DropDownMenu}};
+
+#[derive(Clone)]
+pub enum NamePattern {
+    Glob(glob::Pattern),
+    Regex(regex::Regex)
+}
+
+impl NamePattern {
+    pub fn parse(pattern: &str, regex: bool) -> Result<Self, String> {
+        if regex {
+            regex::RegexBuilder::new(pattern).case_insensitive(true).build().map(NamePattern::Regex).map_err(|error| error.to_string())
+        }
+        else {
+            glob::Pattern::new(pattern).map(NamePattern::Glob).map_err(|error| error.to_string())
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => {
+                let options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+                pattern.matches_with(name, options)
+            },
+            // a regex only has to match a part of the name, like grep
+            NamePattern::Regex(regex) => regex.is_match(name)
+        }
+    }
+}
+
+/// Limits the images that are browsed, everything passes the default filter.
+#[derive(Clone, Default)]
+pub struct ImageFilter {
+    // empty means every format
+    pub formats: Vec<FileFormat>,
+    pub name: Option<NamePattern>,
+    // in pixels, 0 means there is no limit
+    pub min_size: [u32; 2],
+    pub max_size: [u32; 2],
+    // files that start with a dot
+    pub hide_hidden: bool
+}
+
+impl ImageFilter {
+    pub fn is_active(&self) -> bool {
+        !self.formats.is_empty() || self.name.is_some() || self.hide_hidden || self.needs_dimensions()
+    }
+
+    pub fn needs_dimensions(&self) -> bool {
+        self.min_size != [0, 0] || self.max_size != [0, 0]
+    }
+
+    /// `dimensions` is only called when there are size limits, images without known dimensions pass them.
+    pub fn matches(&self, path: &Path, dimensions: impl FnOnce() -> Option<(u32, u32)>) -> bool {
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        if self.hide_hidden && name.starts_with('.') {
+            return false;
+        }
+        if self.name.as_ref().is_some_and(|pattern| !pattern.matches(&name)) {
+            return false;
+        }
+        if !self.formats.is_empty() && !FileFormat::from_path(path).is_ok_and(|format| self.formats.contains(&format)) {
+            return false;
+        }
+        if self.needs_dimensions() {
+            if let Some((width, height)) = dimensions() {
+                let within = |size: u32, min: u32, max: u32| size >= min && (max == 0 || size <= max);
+                return within(width, self.min_size[0], self.max_size[0]) && within(height, self.min_size[1], self.max_size[1]);
+            }
+        }
+        true
+    }
+}
+
+/// The controls of the filter, the pattern is kept as text so it can be edited while it's invalid.
+#[derive(Default)]
+pub struct FilterBar {
+    pub filter: ImageFilter,
+    pattern: String,
+    regex: bool,
+    pattern_error: Option<String>
+}
+
+impl FilterBar {
+    /// Returns true when the filter changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut pattern_changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Name");
+            let hint = if self.regex { "Regular expression" } else { "Pattern like *.png" };
+            pattern_changed |= ui.add(egui::TextEdit::singleline(&mut self.pattern).hint_text(hint).desired_width(140.0)).changed();
+            pattern_changed |= ui.toggle_value(&mut self.regex, ".*").on_hover_text("Regular expression").changed();
+
+            let formats = match self.filter.formats.as_slice() {
+                [] => String::from("All formats"),
+                [format] => format.name(),
+                formats => format!("{} formats", formats.len())
+            };
+            DropDownMenu::new(formats, "filter_formats")
+                .width(90.0)
+                .menu_width(110.0)
+                .ui(ui, |ui| {
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        if ui.add(Button::new("All formats").selected(self.filter.formats.is_empty())).clicked() {
+                            self.filter.formats.clear();
+                            changed = true;
+                        }
+                        for format in FileFormat::iterator().filter(|format| format.can_read()) {
+                            let selected = self.filter.formats.contains(&format);
+                            if ui.add(Button::new(format.name()).selected(selected)).clicked() {
+                                if selected {
+                                    self.filter.formats.retain(|other| *other != format);
+                                }
+                                else {
+                                    self.filter.formats.push(format);
+                                }
+                                changed = true;
+                            }
+                        }
+                    });
+                });
+
+            for (label, axis) in [("Width", 0), ("Height", 1)] {
+                ui.label(label);
+                changed |= ui.add(egui::DragValue::new(&mut self.filter.min_size[axis]).speed(10.0).custom_formatter(size_limit)).on_hover_text("Minimum").changed();
+                ui.label("-");
+                changed |= ui.add(egui::DragValue::new(&mut self.filter.max_size[axis]).speed(10.0).custom_formatter(size_limit)).on_hover_text("Maximum").changed();
+            }
+
+            changed |= ui.checkbox(&mut self.filter.hide_hidden, "Hide hidden files").changed();
+            if ui.add_enabled(self.filter.is_active() || !self.pattern.is_empty(), egui::Button::new("Clear")).clicked() {
+                *self = Self::default();
+                changed = true;
+            }
+            if let Some(error) = self.pattern_error.as_ref() {
+                ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color)).on_hover_text(error);
+            }
+        });
+        if pattern_changed {
+            // an invalid pattern doesn't filter anything until it's fixed
+            let pattern = Some(self.pattern.trim()).filter(|pattern| !pattern.is_empty()).map(|pattern| NamePattern::parse(pattern, self.regex)).transpose();
+            self.pattern_error = pattern.as_ref().err().map(|_| String::from("Invalid pattern"));
+            self.filter.name = pattern.unwrap_or(None);
+            changed = true;
+        }
+        changed
+    }
+}
+
+fn size_limit(value: f64, _: std::ops::RangeInclusive<usize>) -> String {
+    if value == 0.0 { String::from("any") } else { format!("{value}") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_images() {
+        let filter = ImageFilter { name: Some(NamePattern::parse("img_*", false).unwrap()), hide_hidden: true, ..Default::default() };
+        assert!(filter.matches(Path::new("/photos/IMG_1.png"), || None));
+        assert!(!filter.matches(Path::new("/photos/other.png"), || None));
+        assert!(!filter.matches(Path::new("/photos/.img_2.png"), || None));
+
+        let filter = ImageFilter { formats: vec![FileFormat::Svg], min_size: [100, 0], ..Default::default() };
+        assert!(filter.matches(Path::new("icon.svg"), || Some((100, 20))));
+        assert!(!filter.matches(Path::new("icon.svg"), || Some((99, 20))));
+        assert!(!filter.matches(Path::new("icon.png"), || Some((100, 20))));
+
+        let filter = ImageFilter { name: Some(NamePattern::parse(r"\d{4}", true).unwrap()), ..Default::default() };
+        assert!(filter.matches(Path::new("scan 2023.jpg"), || None));
+        assert!(!filter.matches(Path::new("scan.jpg"), || None));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{path::{Path, PathBuf}, fs, io, sync::{mpsc::{self, Receiver, TryRecvError}, Arc, atomic::{AtomicBool, Ordering}}, time::SystemTime};
+
+use image::{DynamicImage, ImageError, ImageResult, error::{DecodingError, ImageFormatHint}};
+
+use crate::{animation::{AnimationFrame, decode_frames}, file_format::FileFormat, svg::SvgDocument, photo_metadata::PhotoMetadata, color_profile::ColorProfile, raw, pages::{Pages, decode_pages}, tiled_texture::TiledImage, tone_mapping::ToneMapping, egui_extensions::color_image};
+#[cfg(feature = "heif")]
+use crate::heif;
+
+// the pixels are shared with the cache and the opened image, they are never changed
+#[derive(Clone)]
+pub struct DecodedImage {
+    pub image: Arc<DynamicImage>,
+    // empty if the image isn't animated
+    pub frames: Arc<[AnimationFrame]>,
+    // for multi page files, the image is the first page
+    pub pages: Option<Pages>,
+    pub inner_format: Option<FileFormat>,
+    pub file_format: Option<FileFormat>,
+    pub svg: Option<Arc<SvgDocument>>,
+    pub photo_metadata: Option<PhotoMetadata>,
+    // the pixels are kept in the source color space, they are converted for the display
+    pub color_profile: Option<Arc<ColorProfile>>,
+    // set by the loader, the ui thread only uploads it
+    pub display: Option<DisplayImage>
+}
+
+impl DecodedImage {
+    pub fn memory_size(&self) -> usize {
+        let pages_size = self.pages.as_ref().map_or(0, |pages| pages.memory_size());
+        let display_size = self.display.as_ref().map_or(0, |display| display.tiles.memory_size());
+        self.frames.iter().fold(self.image.as_bytes().len() + pages_size + display_size, |size, frame| size + frame.image.as_bytes().len())
+    }
+
+    /// The prepared display image if it still looks right with the `tone_mapping`.
+    pub fn display_for(&self, tone_mapping: &ToneMapping) -> Option<&TiledImage> {
+        self.display.as_ref()
+            .filter(|display| display.tone_mapping == *tone_mapping || !ToneMapping::applies_to(&self.image))
+            .map(|display| &display.tiles)
+    }
+}
+
+/// The image in the colors of the screen, split into the tiles of its texture.
+#[derive(Clone)]
+pub struct DisplayImage {
+    pub tiles: TiledImage,
+    // hdr images need a new one when the tone mapping changes
+    pub tone_mapping: ToneMapping
+}
+
+impl DisplayImage {
+    fn new(image: &DynamicImage, color_profile: Option<&ColorProfile>, tone_mapping: ToneMapping, max_side: usize) -> Self {
+        Self { tiles: TiledImage::new(color_image(image, color_profile, &tone_mapping), max_side), tone_mapping }
+    }
+}
+
+/// Set when nobody is waiting for the image anymore, the decoding stops before its next stage.
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// The error is never shown, a cancelled load doesn't send its result.
+    pub fn check(&self) -> ImageResult<()> {
+        if self.0.load(Ordering::Relaxed) {
+            Err(ImageError::IoError(io::Error::from(io::ErrorKind::Interrupted)))
+        } else { Ok(()) }
+    }
+}
+
+pub fn decode_file(path: &Path) -> ImageResult<DecodedImage> {
+    let bytes = fs::read(path).map_err(image::ImageError::IoError)?;
+    decode_bytes(&bytes, FileFormat::from_path(path), &Cancellation::default())
+}
+
+pub fn decode_bytes(bytes: &[u8], format_hint: ImageResult<FileFormat>, cancellation: &Cancellation) -> ImageResult<DecodedImage> {
+    let guessed_format = FileFormat::guess(bytes);
+    let inner_format = guessed_format.as_ref().ok().cloned();
+    let file_format = format_hint.as_ref().ok().cloned();
+    let format = match (guessed_format, format_hint) {
+        // the raw extension decides for tiff files that don't look like raw files by their tags
+        (Ok(FileFormat::Raster(image::ImageFormat::Tiff)), Ok(FileFormat::Raw(format))) => FileFormat::Raw(format),
+        (guessed_format, format_hint) => guessed_format.or(format_hint)?
+    };
+    match format {
+        #[cfg(feature = "heif")]
+        FileFormat::Raster(image::ImageFormat::Avif) | FileFormat::Heif => {
+            let photo_metadata = PhotoMetadata::read(bytes);
+            // libheif applies the rotation stored in the file, the exif orientation isn't applied again
+            let (image, color_profile) = heif::decode(bytes)?;
+            Ok(DecodedImage { image: Arc::new(image), frames: Arc::default(), pages: None, inner_format, file_format, svg: None, photo_metadata, color_profile: color_profile.map(Arc::new), display: None })
+        },
+        #[cfg(not(feature = "heif"))]
+        FileFormat::Heif => Err(image::ImageError::Unsupported(image::error::ImageFormatHint::Name(String::from("HEIF")).into())),
+        FileFormat::Raster(format) => {
+            let photo_metadata = PhotoMetadata::read(bytes);
+            let color_profile = ColorProfile::read(bytes, format).map(Arc::new);
+            // phone cameras store the pixels sideways and rely on the orientation tag
+            let orientation = photo_metadata.as_ref().map(|metadata| metadata.orientation).unwrap_or_default();
+            cancellation.check()?;
+            let frames: Arc<[AnimationFrame]> = decode_frames(bytes, format, cancellation)?
+                .into_iter()
+                .map(|frame| AnimationFrame { image: orientation.apply(frame.image), ..frame })
+                .collect();
+            cancellation.check()?;
+            let pages = decode_pages(bytes, format).into_iter().map(|page| orientation.apply(page)).collect();
+            let (image, pages) = match (Pages::split_first(pages), frames.first()) {
+                (Some((first, pages)), _) => (first, Some(pages)),
+                // animations start on their first frame, the file isn't decoded a second time for it
+                (None, Some(frame)) => (Arc::new(frame.image.clone()), None),
+                (None, None) => (Arc::new(orientation.apply(image::load_from_memory_with_format(bytes, format)?)), None)
+            };
+            Ok(DecodedImage { image, frames, pages, inner_format, file_format, svg: None, photo_metadata, color_profile, display: None })
+        },
+        FileFormat::Raw(_) => {
+            let photo_metadata = PhotoMetadata::read(bytes);
+            // the sensor data and the previews are stored the way the camera was held, like jpeg photos
+            let orientation = photo_metadata.as_ref().map(|metadata| metadata.orientation).unwrap_or_default();
+            // some of them are ordinary tiff images, like the ones cameras save next to the raw files
+            let image = raw::decode(bytes)
+                .or_else(|error| image::load_from_memory_with_format(bytes, image::ImageFormat::Tiff).map_err(|_| error))?;
+            let image = Arc::new(orientation.apply(image));
+            Ok(DecodedImage { image, frames: Arc::default(), pages: None, inner_format, file_format, svg: None, photo_metadata, color_profile: None, display: None })
+        },
+        FileFormat::Svg => {
+            let document = SvgDocument::parse(bytes)?;
+            let image = document.render_image(document.base_scale())?;
+            Ok(DecodedImage { image: Arc::new(image), frames: Arc::default(), pages: None, inner_format, file_format, svg: Some(Arc::new(document)), photo_metadata: None, color_profile: None, display: None })
+        }
+    }
+}
+
+enum LoadSource {
+    File,
+    Bytes(Arc<[u8]>),
+    // only the display image is missing
+    Decoded(Box<DecodedImage>)
+}
+
+/// Decodes images and converts them for the display on a worker thread so the ui doesn't freeze while big images are loading.
+pub struct ImageLoader {
+    path: PathBuf,
+    source: LoadSource
+}
+
+impl ImageLoader {
+    pub fn file(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), source: LoadSource::File }
+    }
+
+    pub fn bytes(path: &Path, bytes: Arc<[u8]>) -> Self {
+        Self { path: path.to_path_buf(), source: LoadSource::Bytes(bytes) }
+    }
+
+    /// For images that are already decoded, like cached ones from before the tone mapping changed.
+    pub fn decoded(path: &Path, decoded: DecodedImage) -> Self {
+        Self { path: path.to_path_buf(), source: LoadSource::Decoded(Box::new(decoded)) }
+    }
+
+    /// The display image is made with the `tone_mapping`.
+    pub fn load(self, context: &egui::Context, tone_mapping: &ToneMapping) -> LoadHandle {
+        let (sender, receiver) = mpsc::channel();
+        let cancellation = Cancellation::default();
+        let context = context.clone();
+        let path = self.path.clone();
+        let modified = match self.source {
+            LoadSource::File | LoadSource::Decoded(_) => fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok(),
+            LoadSource::Bytes(_) => None,
+        };
+        let tone_mapping = *tone_mapping;
+        let max_side = context.input(|input| input.max_texture_side);
+        let thread_cancellation = cancellation.clone();
+        let thread_handle = std::thread::spawn(move || {
+            let format = FileFormat::from_path(&self.path);
+            let result = match self.source {
+                LoadSource::File => fs::read(&self.path)
+                    .map_err(ImageError::IoError)
+                    .and_then(|bytes| decode_bytes(&bytes, format, &thread_cancellation)),
+                LoadSource::Bytes(bytes) => decode_bytes(&bytes, format, &thread_cancellation),
+                LoadSource::Decoded(decoded) => Ok(*decoded)
+            };
+            let result = result.and_then(|mut decoded| {
+                thread_cancellation.check()?;
+                decoded.display = Some(DisplayImage::new(&decoded.image, decoded.color_profile.as_deref(), tone_mapping, max_side));
+                Ok(decoded)
+            });
+            // a newer image was requested while decoding, nobody is waiting for this one anymore
+            if thread_cancellation.check().is_ok() {
+                let _ = sender.send(result);
+                context.request_repaint();
+            }
+        });
+        LoadHandle { path, modified, cancellation, _thread_handle: thread_handle, receiver }
+    }
+}
+
+pub struct LoadHandle {
+    path: PathBuf,
+    // the modification time of the file when the load started
+    modified: Option<SystemTime>,
+    cancellation: Cancellation,
+    _thread_handle: std::thread::JoinHandle<()>,
+    receiver: Receiver<ImageResult<DecodedImage>>
+}
+
+impl LoadHandle {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// `None` until the image is decoded, a decoder that panicked is reported as an error.
+    pub fn image_decoded(&self) -> Option<ImageResult<DecodedImage>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(ImageError::Decoding(DecodingError::new(
+                ImageFormatHint::PathExtension(self.path.extension().map(PathBuf::from).unwrap_or_default()),
+                "The decoder stopped without a result."
+            ))))
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+impl Drop for LoadHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Converts an image that is already shown for the display on a worker thread, like after the tone mapping changed.
+/// The result is dropped with the handle.
+pub struct DisplayHandle {
+    receiver: Receiver<TiledImage>
+}
+
+impl DisplayHandle {
+    pub fn start(context: &egui::Context, image: Arc<DynamicImage>, color_profile: Option<Arc<ColorProfile>>, tone_mapping: &ToneMapping) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let context = context.clone();
+        let tone_mapping = *tone_mapping;
+        let max_side = context.input(|input| input.max_texture_side);
+        std::thread::spawn(move || {
+            let display = DisplayImage::new(&image, color_profile.as_deref(), tone_mapping, max_side);
+            if sender.send(display.tiles).is_ok() {
+                context.request_repaint();
+            }
+        });
+        Self { receiver }
+    }
+
+    pub fn try_recv(&self) -> Result<TiledImage, TryRecvError> {
+        self.receiver.try_recv()
+    }
+}
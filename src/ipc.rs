@@ -0,0 +1,214 @@
+use std::{fs::DirBuilder, io::{self, BufRead, BufReader, Write}, os::unix::{fs::{DirBuilderExt, PermissionsExt}, net::{UnixListener, UnixStream}}, path::{Path, PathBuf}, sync::mpsc::{self, Receiver, Sender}, time::Duration};
+
+use crate::cli::{Zoom, parse_zoom};
+
+// how long a connection waits for the app to answer, the app only answers while it's drawing frames
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The commands of the control socket, one per line.
+/// `open` takes tab separated paths, every command is answered with a line that starts with `ok` or `error`.
+pub enum Command {
+    Open(Vec<PathBuf>),
+    Next,
+    Previous,
+    Zoom(Zoom),
+    ZoomIn,
+    ZoomOut,
+    Rotate,
+    Quit,
+    Current
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        match (name, argument.trim()) {
+            ("open", "") => Err(String::from("open needs a path")),
+            ("open", paths) => Ok(Command::Open(paths.split('\t').map(PathBuf::from).collect())),
+            ("next", "") => Ok(Command::Next),
+            ("previous", "") => Ok(Command::Previous),
+            ("zoom", "in") => Ok(Command::ZoomIn),
+            ("zoom", "out") => Ok(Command::ZoomOut),
+            ("zoom", zoom) => parse_zoom(zoom).map(Command::Zoom),
+            ("rotate", "") => Ok(Command::Rotate),
+            ("quit", "") => Ok(Command::Quit),
+            ("current", "") => Ok(Command::Current),
+            _ => Err(format!("unknown command '{line}'"))
+        }
+    }
+}
+
+/// A command from the socket, the app answers it with `reply`.
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Result<Option<String>, String>>
+}
+
+impl Request {
+    /// `Ok(Some(value))` is sent as `ok value`.
+    pub fn reply(self, result: Result<Option<String>, String>) {
+        // the connection might have timed out already
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Listens on the control socket, the socket file is removed when it's dropped.
+pub struct Server {
+    path: PathBuf,
+    receiver: Receiver<Request>
+}
+
+impl Server {
+    pub fn start(context: &egui::Context) -> io::Result<Self> {
+        let path = socket_path();
+        private_directory(&path)?;
+        let listener = match UnixListener::bind(&path) {
+            // a previous instance crashed without removing it
+            Err(error) if error.kind() == io::ErrorKind::AddrInUse && UnixStream::connect(&path).is_err() => {
+                std::fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            },
+            result => result?
+        };
+        let (sender, receiver) = mpsc::channel();
+        let context = context.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                let context = context.clone();
+                std::thread::spawn(move || {
+                    if let Err(error) = serve(stream, &sender, &context) {
+                        eprintln!("Control socket connection failed: {error}");
+                    }
+                });
+            }
+        });
+        Ok(Self { path, receiver })
+    }
+
+    pub fn try_recv(&self) -> Option<Request> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, sender: &Sender<Request>, context: &egui::Context) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                if sender.send(Request { command, reply }).is_err() {
+                    return Ok(());
+                }
+                context.request_repaint();
+                answer.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| Err(String::from("the viewer didn't answer")))
+            },
+            Err(error) => Err(error)
+        };
+        match result {
+            Ok(Some(value)) => writeln!(writer, "ok {value}")?,
+            Ok(None) => writeln!(writer, "ok")?,
+            Err(error) => writeln!(writer, "error {error}")?
+        }
+    }
+    Ok(())
+}
+
+/// Opens the paths in the running instance, returns false if there isn't one.
+/// Without paths a new window is opened like before.
+pub fn forward_paths(paths: &[PathBuf]) -> bool {
+    if paths.is_empty() {
+        return false;
+    }
+    let path = socket_path();
+    // another user could be listening in a shared directory
+    if private_directory(&path).is_err() {
+        return false;
+    }
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return false;
+    };
+    // the running instance has a different working directory
+    let paths: Vec<String> = paths.iter()
+        .map(|path| absolute(path).to_string_lossy().to_string())
+        .collect();
+    let sent = writeln!(stream, "open {}", paths.join("\t"));
+    let mut answer = String::new();
+    let answered = sent.and_then(|_| BufReader::new(stream).read_line(&mut answer));
+    if answered.is_err() || !answer.starts_with("ok") {
+        eprintln!("The running instance couldn't open the images: {}", answer.trim());
+    }
+    true
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| std::env::current_dir().map(|directory| directory.join(path)).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// In the runtime directory when there is one, otherwise in a directory for the user in the temp directory.
+/// `private_directory` makes sure only the user can access either of them.
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(directory) => PathBuf::from(directory).join("enchanted-view.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir().join(format!("enchanted-view-{user}")).join("enchanted-view.sock")
+        }
+    }
+}
+
+/// Creates the directory of the socket with only user access, fails if it already exists and others can access it.
+fn private_directory(socket: &Path) -> io::Result<()> {
+    let Some(directory) = socket.parent() else { return Ok(()) };
+    match DirBuilder::new().mode(0o700).create(directory) {
+        Err(error) if error.kind() != io::ErrorKind::AlreadyExists => return Err(error),
+        _ => ()
+    }
+    // a symlink could point anywhere, another user's directory isn't accessible to us when it's private
+    let metadata = std::fs::symlink_metadata(directory)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is accessible to other users", directory.display())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(Command::parse("next"), Ok(Command::Next)));
+        assert!(matches!(Command::parse("zoom 200%\n"), Ok(Command::Zoom(Zoom::Scale(scale))) if scale == 2.0));
+        assert!(matches!(Command::parse("zoom in"), Ok(Command::ZoomIn)));
+        match Command::parse("open /a b.png\t/c.png") {
+            Ok(Command::Open(paths)) => assert_eq!(paths, [PathBuf::from("/a b.png"), PathBuf::from("/c.png")]),
+            _ => panic!("open should be parsed")
+        }
+        assert!(Command::parse("open").is_err());
+        assert!(Command::parse("next 2").is_err());
+        assert!(Command::parse("jump").is_err());
+    }
+
+    #[test]
+    fn rejects_shared_directories() {
+        let directory = std::env::temp_dir().join(format!("enchanted-view-ipc-{}", std::process::id()));
+        let socket = directory.join("enchanted-view.sock");
+        private_directory(&socket).unwrap();
+        assert_eq!(std::fs::metadata(&directory).unwrap().permissions().mode() & 0o777, 0o700);
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_directory(&socket).is_err());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+// the markers that matter for lossless jpeg, everything else is skipped
+const MARKER_SOF3: u8 = 0xC3;
+const MARKER_DHT: u8 = 0xC4;
+const MARKER_SOS: u8 = 0xDA;
+const MARKER_EOI: u8 = 0xD9;
+// codes up to this length are decoded with one table lookup
+const LOOKUP_BITS: u32 = 9;
+
+/// The samples of a lossless jpeg (ITU T.81 process 14), the compression dng uses for its sensor data.
+/// Every row has `width * components` samples with the components interleaved.
+pub struct LosslessJpeg {
+    pub width: usize,
+    pub height: usize,
+    pub components: usize,
+    pub samples: Vec<u16>
+}
+
+impl LosslessJpeg {
+    /// `None` if the data isn't a lossless jpeg or it's broken.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(0..2)? != [0xFF, 0xD8] {
+            return None;
+        }
+        let mut tables: [Option<HuffmanTable>; 4] = Default::default();
+        let mut frame = None;
+        let mut position = 2;
+        loop {
+            // fill bytes can come before a marker
+            while *bytes.get(position)? == 0xFF && *bytes.get(position + 1)? == 0xFF {
+                position += 1;
+            }
+            if *bytes.get(position)? != 0xFF {
+                return None;
+            }
+            let marker = *bytes.get(position + 1)?;
+            if marker == MARKER_EOI {
+                return None;
+            }
+            let length = u16::from_be_bytes([*bytes.get(position + 2)?, *bytes.get(position + 3)?]) as usize;
+            let segment = bytes.get(position + 4..position + 2 + length)?;
+            match marker {
+                MARKER_SOF3 => frame = Some(Frame::read(segment)?),
+                // the other frame types are lossy jpegs
+                0xC0..=0xCF if marker != MARKER_DHT && marker != 0xC8 && marker != 0xCC => return None,
+                MARKER_DHT => HuffmanTable::read_all(segment, &mut tables)?,
+                MARKER_SOS => {
+                    let frame = frame?;
+                    let scan = Scan::read(segment, &frame)?;
+                    let tables: Vec<&HuffmanTable> = scan.tables.iter().map(|index| tables.get(*index)?.as_ref()).collect::<Option<_>>()?;
+                    let data = &bytes[position + 2 + length..];
+                    return Self::decode_scan(&frame, &scan, &tables, data);
+                },
+                _ => ()
+            }
+            position += 2 + length;
+        }
+    }
+
+    fn decode_scan(frame: &Frame, scan: &Scan, tables: &[&HuffmanTable], data: &[u8]) -> Option<Self> {
+        let components = frame.components;
+        let row_length = frame.width.checked_mul(components)?;
+        let sample_count = row_length.checked_mul(frame.height)?;
+        // every sample takes at least one bit, a smaller scan is broken
+        if sample_count / 8 > data.len() {
+            return None;
+        }
+        let mut samples = vec![0u16; sample_count];
+        let mut reader = BitReader::new(data);
+        let initial = 1i32 << (frame.precision - scan.point_transform - 1);
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                for (component, table) in tables.iter().enumerate() {
+                    let index = y * row_length + x * components + component;
+                    let left = || samples[index - components] as i32;
+                    let above = || samples[index - row_length] as i32;
+                    let prediction = match (x, y) {
+                        (0, 0) => initial,
+                        (_, 0) => left(),
+                        (0, _) => above(),
+                        _ => {
+                            let (a, b, c) = (left(), above(), samples[index - row_length - components] as i32);
+                            match scan.predictor {
+                                1 => a,
+                                2 => b,
+                                3 => c,
+                                4 => a + b - c,
+                                5 => a + ((b - c) >> 1),
+                                6 => b + ((a - c) >> 1),
+                                _ => (a + b) >> 1
+                            }
+                        }
+                    };
+                    let difference = reader.difference(table)?;
+                    samples[index] = (prediction + difference) as u16;
+                }
+            }
+        }
+        if scan.point_transform > 0 {
+            samples.iter_mut().for_each(|sample| *sample <<= scan.point_transform);
+        }
+        Some(Self { width: frame.width, height: frame.height, components, samples })
+    }
+}
+
+struct Frame {
+    precision: u32,
+    width: usize,
+    height: usize,
+    components: usize,
+    component_ids: Vec<u8>
+}
+
+impl Frame {
+    fn read(segment: &[u8]) -> Option<Self> {
+        let precision = *segment.first()? as u32;
+        let height = u16::from_be_bytes([*segment.get(1)?, *segment.get(2)?]) as usize;
+        let width = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]) as usize;
+        let components = *segment.get(5)? as usize;
+        let component_ids = (0..components).map(|index| segment.get(6 + index * 3).copied()).collect::<Option<Vec<u8>>>()?;
+        // subsampled components like in canon's sraw aren't supported
+        let subsampled = (0..components).any(|index| segment.get(7 + index * 3) != Some(&0x11));
+        if !(2..=16).contains(&precision) || width == 0 || height == 0 || components == 0 || subsampled {
+            return None;
+        }
+        Some(Self { precision, width, height, components, component_ids })
+    }
+}
+
+struct Scan {
+    // the huffman table of every frame component
+    tables: Vec<usize>,
+    predictor: u8,
+    point_transform: u32
+}
+
+impl Scan {
+    fn read(segment: &[u8], frame: &Frame) -> Option<Self> {
+        let count = *segment.first()? as usize;
+        // every component has to be in the one scan
+        if count != frame.components {
+            return None;
+        }
+        let mut tables = vec![0; count];
+        for index in 0..count {
+            let id = *segment.get(1 + index * 2)?;
+            let component = frame.component_ids.iter().position(|component_id| *component_id == id)?;
+            tables[component] = (*segment.get(2 + index * 2)? >> 4) as usize;
+        }
+        let predictor = *segment.get(1 + count * 2)?;
+        let point_transform = (*segment.get(3 + count * 2)? & 0x0F) as u32;
+        if !(1..=7).contains(&predictor) || point_transform >= frame.precision {
+            return None;
+        }
+        Some(Self { tables, predictor, point_transform })
+    }
+}
+
+struct HuffmanTable {
+    // the symbol and code length for every LOOKUP_BITS long prefix, a length of 0 means the code is longer
+    lookup: Vec<(u8, u8)>,
+    // the largest code of every length and where its symbols start, for the longer codes
+    max_codes: [i32; 17],
+    offsets: [i32; 17],
+    symbols: Vec<u8>
+}
+
+impl HuffmanTable {
+    fn read_all(segment: &[u8], tables: &mut [Option<HuffmanTable>; 4]) -> Option<()> {
+        let mut position = 0;
+        while position < segment.len() {
+            let index = (segment[position] & 0x0F) as usize;
+            let counts = segment.get(position + 1..position + 17)?;
+            let symbol_count: usize = counts.iter().map(|count| *count as usize).sum();
+            let symbols = segment.get(position + 17..position + 17 + symbol_count)?;
+            *tables.get_mut(index)? = Some(HuffmanTable::new(counts, symbols)?);
+            position += 17 + symbol_count;
+        }
+        Some(())
+    }
+
+    /// `None` if there are more codes of a length than fit in it.
+    fn new(counts: &[u8], symbols: &[u8]) -> Option<Self> {
+        let mut lookup = vec![(0, 0); 1 << LOOKUP_BITS];
+        let mut max_codes = [-1; 17];
+        let mut offsets = [0; 17];
+        let mut code = 0i32;
+        let mut symbol = 0;
+        for length in 1..=16 {
+            offsets[length] = symbol as i32 - code;
+            for _ in 0..counts[length - 1] {
+                if code >= 1 << length {
+                    return None;
+                }
+                if length as u32 <= LOOKUP_BITS {
+                    let shift = LOOKUP_BITS - length as u32;
+                    for suffix in 0..1 << shift {
+                        lookup[((code as usize) << shift) | suffix] = (symbols[symbol], length as u8);
+                    }
+                }
+                code += 1;
+                symbol += 1;
+            }
+            max_codes[length] = code - 1;
+            code <<= 1;
+        }
+        Some(Self { lookup, max_codes, offsets, symbols: symbols.to_vec() })
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u64,
+    bits: u32
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0, buffer: 0, bits: 0 }
+    }
+
+    fn fill(&mut self) {
+        while self.bits <= 56 {
+            let mut byte = 0;
+            if let Some(next) = self.data.get(self.position) {
+                if *next == 0xFF {
+                    // a stuffed zero byte follows a data 0xFF, anything else is the marker that ends the scan
+                    if self.data.get(self.position + 1) == Some(&0) {
+                        byte = 0xFF;
+                        self.position += 2;
+                    }
+                }
+                else {
+                    byte = *next;
+                    self.position += 1;
+                }
+            }
+            self.buffer |= (byte as u64) << (56 - self.bits);
+            self.bits += 8;
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        self.fill();
+        let value = (self.buffer >> (64 - count)) as u32;
+        self.buffer <<= count;
+        self.bits -= count;
+        value
+    }
+
+    /// Reads a huffman coded difference, the symbol is the number of bits of the difference.
+    fn difference(&mut self, table: &HuffmanTable) -> Option<i32> {
+        self.fill();
+        let (mut symbol, length) = table.lookup[(self.buffer >> (64 - LOOKUP_BITS)) as usize];
+        if length > 0 {
+            self.read(length as u32);
+        }
+        else {
+            let mut code = self.read(LOOKUP_BITS) as i32;
+            let mut length = LOOKUP_BITS as usize;
+            while code > table.max_codes[length] {
+                length += 1;
+                if length > 16 {
+                    return None;
+                }
+                code = (code << 1) | self.read(1) as i32;
+            }
+            symbol = *table.symbols.get((table.offsets[length] + code) as usize)?;
+        }
+        Some(match symbol {
+            0 => 0,
+            16 => 32768,
+            bits if bits < 16 => {
+                let value = self.read(bits as u32) as i32;
+                // values with a leading zero bit are negative
+                if value < 1 << (bits - 1) { value - (1 << bits) + 1 } else { value }
+            },
+            _ => return None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the differences with a table where every bit count has a 5 bit code.
+    fn encode(width: u16, height: u16, samples: &[u16]) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend_from_slice(&[0xFF, MARKER_SOF3, 0, 11, 16]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&[1, 0, 0x11, 0]);
+        let mut counts = [0u8; 16];
+        counts[4] = 17;
+        bytes.extend_from_slice(&[0xFF, MARKER_DHT, 0, 2 + 17 + 17, 0]);
+        bytes.extend_from_slice(&counts);
+        bytes.extend((0..17).map(|symbol| symbol as u8));
+        bytes.extend_from_slice(&[0xFF, MARKER_SOS, 0, 8, 1, 0, 0, 1, 0, 0]);
+
+        let mut bits: Vec<bool> = Vec::new();
+        let mut push = |value: u32, count: u32| bits.extend((0..count).rev().map(|bit| (value >> bit) & 1 == 1));
+        let width = width as usize;
+        for (index, sample) in samples.iter().enumerate() {
+            let prediction = match (index % width, index / width) {
+                (0, 0) => 1 << 15,
+                (0, _) => samples[index - width],
+                _ => samples[index - 1]
+            };
+            // the differences wrap around like the samples
+            let difference = sample.wrapping_sub(prediction) as i16 as i32;
+            if difference == i16::MIN as i32 {
+                push(16, 5);
+                continue;
+            }
+            let size = 32 - difference.unsigned_abs().leading_zeros();
+            push(size, 5);
+            let value = if difference < 0 { difference - 1 + (1 << size) } else { difference };
+            push(value as u32, size);
+        }
+        bits.resize(bits.len().div_ceil(8) * 8, true);
+        for byte in bits.chunks(8) {
+            let byte = byte.iter().fold(0u8, |byte, bit| byte << 1 | *bit as u8);
+            bytes.push(byte);
+            if byte == 0xFF {
+                bytes.push(0);
+            }
+        }
+        bytes.extend_from_slice(&[0xFF, MARKER_EOI]);
+        bytes
+    }
+
+    #[test]
+    fn decodes_lossless_jpeg() {
+        // predictor 1 uses the left sample, the first sample of a row uses the one above it
+        let samples = [0, 65535, 1000, 32768, 300, 299, 40000, 40000];
+        let bytes = encode(4, 2, &samples);
+        let decoded = LosslessJpeg::decode(&bytes).expect("It's a lossless jpeg.");
+        assert_eq!((decoded.width, decoded.height, decoded.components), (4, 2, 1));
+        assert_eq!(decoded.samples, samples);
+        assert!(LosslessJpeg::decode(&bytes[..20]).is_none());
+    }
+}
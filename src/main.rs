@@ -1,11 +1,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::{path::PathBuf, io, fs};
+use std::{path::{Path, PathBuf}, io, fs, sync::{Arc, mpsc::TryRecvError}};
 
-use color_analyzer::ColorAnalyzer;
+use color_analyzer::{ColorAnalyzer, PixelSample};
 use egui::{Layout, TextureFilter, TextureOptions};
 use file_dialog::{FileDialogHandle, FileDialog};
-use image::{DynamicImage, ImageResult, ImageError, ImageFormat, GenericImageView};
+use image::{DynamicImage, ImageResult, ImageError};
 mod widgets;
 mod egui_extensions;
 mod pan_zoom_image;
@@ -18,16 +18,76 @@ mod color_name;
 mod key_value_match;
 mod color_analyzer;
 mod utilities;
+mod image_loader;
+mod image_cache;
+mod animation;
+mod conversion;
+mod file_format;
+mod svg;
+mod tiled_texture;
+mod photo_metadata;
+mod color_profile;
+mod tone_mapping;
+mod raw;
+mod lossless_jpeg;
+mod pages;
+#[cfg(feature = "heif")]
+mod heif;
+mod clipboard;
+mod cli;
+mod slideshow;
+mod thumbnails;
+mod thumbnail_cache;
+mod session;
+mod sort_order;
+mod image_filter;
+#[cfg(unix)]
+mod ipc;
 use image_directory::{ImageDirectory, ImageFormatEx, is_image_file};
 use settings::Settings;
 use egui_extensions::{ContextEx, ImageLoadResult};
+use image_loader::{ImageLoader, LoadHandle, DecodedImage, DisplayHandle};
+use image_cache::{ImageCache, CacheKey};
+use animation::{AnimationFrame, PLAYBACK_SPEEDS};
+use file_format::FileFormat;
+use svg::{SvgView, SvgElement};
+use tiled_texture::{TiledImage, TiledTexture};
+use photo_metadata::PhotoMetadata;
+use color_profile::ColorProfile;
+use tone_mapping::{ToneMapping, ToneMapOperator};
+use pages::Pages;
+use clipboard::{ClipboardImage, SystemClipboard};
+use cli::{Cli, Command, SessionOverrides, Zoom};
+use clap::Parser;
+use slideshow::{Slideshow, SlideshowOptions, Transition};
+use thumbnails::Thumbnails;
+use thumbnail_cache::ThumbnailSize;
+use session::{RecentFiles, LastSession};
+use sort_order::SortKey;
+use image_filter::FilterBar;
+use conversion::{save_image, export_frames, transform_image, FrameSelection, DEFAULT_FRAME_NAME_PATTERN};
 use pan_zoom_image::PanZoomImage;
 use theme::Theme;
 use utilities::{format_bytes, format_path};
-use widgets::{CenterContainer, Button, ImageButton, close_button, DropDownMenu, ComboBox, PathPickerState, PathPicker};
+use widgets::{CenterContainer, Button, ImageButton, close_button, DropDownMenu, ComboBox, PathPickerState, PathPicker, select, RadioValue};
+
+// pasted images aren't files, this is shown instead of the file name
+const PASTED_IMAGE_NAME: &str = "Pasted image";
+// the recent images and the filmstrip share the thumbnails, the filmstrip shows them smaller
+const RECENT_THUMBNAIL_SIZE: f32 = 96.0;
+const FILMSTRIP_HEIGHT: f32 = 56.0;
 
 fn main() -> Result<(), eframe::Error> {
+    let cli = Cli::parse();
+    if let Some(Command::Convert(args)) = cli.command.as_ref() {
+        std::process::exit(args.run());
+    }
+    #[cfg(unix)]
+    if cli.single_instance && ipc::forward_paths(&cli.paths) {
+        return Ok(());
+    }
     let options = eframe::NativeOptions {
+        fullscreen: cli.fullscreen,
         initial_window_size: Some(egui::vec2(600.0, 800.0)),
         min_window_size: Some(egui::vec2(300.0, 200.0)),
         icon_data: Some(eframe::IconData::try_from_png_bytes(include_bytes!("../assets/icon.png")).expect("The icon should be valid.")),
@@ -39,7 +99,7 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(|cc| {
             // This gives us image support:
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Box::new(EnchantedView::new(cc.egui_ctx.clone()))
+            Box::new(EnchantedView::new(cc.egui_ctx.clone(), cli))
         }),
     )
 }
@@ -51,21 +111,23 @@ struct ImageMetadata {
     date_modified: Option<chrono::DateTime<chrono::offset::Local>>,
     date_accessed: Option<chrono::DateTime<chrono::offset::Local>>,
     read_only: Option<bool>,
-    file_format: Option<image::ImageFormat>,
-    inner_format: Option<image::ImageFormat>
+    file_format: Option<FileFormat>,
+    inner_format: Option<FileFormat>,
+    photo_metadata: Option<PhotoMetadata>
 }
 
 impl ImageMetadata {
     fn image_format_display(&self) -> String {
         self.inner_format
             .or(self.file_format)
-            .and_then(|format| Some(format!("{format:?}").to_uppercase()))
+            .map(|format| format.name())
             .unwrap_or_else(|| String::from("--"))
     }
 
     fn image_format(&self) -> Option<image::ImageFormat> {
         self.inner_format
             .or(self.file_format)
+            .and_then(|format| format.raster())
     }
 
     fn image_format_different(&self) -> bool {
@@ -75,19 +137,15 @@ impl ImageMetadata {
     }
 
     fn inner_format_display(&self) -> String {
-        self.inner_format.as_ref().and_then(|format| {
-            let mut inner_format_str = format!("{format:?}");
-            inner_format_str.make_ascii_uppercase();
-            Some(inner_format_str)
-        }).unwrap_or_else(|| String::from("--"))
+        self.inner_format.as_ref()
+            .map(|format| format.name())
+            .unwrap_or_else(|| String::from("--"))
     }
 
     fn file_format_display(&self) -> String {
-        self.file_format.as_ref().and_then(|format| {
-            let mut file_format_str = format!("{format:?}");
-            file_format_str.make_ascii_uppercase();
-            Some(file_format_str)
-        }).unwrap_or_else(|| String::from("--"))
+        self.file_format.as_ref()
+            .map(|format| format.name())
+            .unwrap_or_else(|| String::from("--"))
     }
 
     fn format_ext(&self) -> Option<&str> {
@@ -97,18 +155,66 @@ impl ImageMetadata {
     }
 }
 struct OpenedImage {
-    image: DynamicImage,
+    image: Arc<DynamicImage>,
+    // empty if the image isn't animated
+    frames: Arc<[AnimationFrame]>,
+    // for multi page files, the image is the current page
+    pages: Option<Pages>,
+    svg: Option<SvgView>,
+    color_profile: Option<Arc<ColorProfile>>,
+    // the texture of the image before it was uploaded
+    tiles: TiledImage,
+    // set while the image is converted again for the display, after a page switch or a tone mapping change
+    pending_display: Option<DisplayHandle>,
     display: PanZoomImage,
     metadata: ImageMetadata
 }
 
+impl OpenedImage {
+    /// The image that is currently displayed, for animations it's the current frame.
+    fn current_image(&self) -> &DynamicImage {
+        self.display.animation.as_ref()
+            .and_then(|animation| self.frames.get(animation.current_frame()))
+            .map(|frame| &frame.image)
+            .unwrap_or(&self.image)
+    }
+}
+
 struct ImageInfoState {
     rename: Option<String>,
     selected_format: image::ImageFormat,
     format_ext: String,
     path_picker_state: PathPickerState,
     target_path: PathBuf,
-    target_name: String
+    target_name: String,
+    frame_export: FrameExportState
+}
+
+struct FrameExportState {
+    selection: FrameSelection,
+    // 1 based like the frame counter in the animation bar
+    range_start: usize,
+    range_end: usize,
+    // the range is reset to every frame when another animation with a different frame count is shown
+    frame_count: usize,
+    name_pattern: String,
+    // how many frames the last export wrote
+    exported: Option<usize>
+}
+
+/// Dragging over the image selects the region that is copied.
+#[derive(Default)]
+struct RegionSelection {
+    // the corners of the drag in texture pixels
+    start: Option<egui::Pos2>,
+    end: egui::Pos2
+}
+
+#[derive(PartialEq)]
+enum Screen {
+    Main,
+    Settings,
+    Gallery
 }
 
 struct ErrorWindow {
@@ -118,95 +224,499 @@ struct ErrorWindow {
 
 struct EnchantedView {
     image: Result<OpenedImage, Option<String>>,
+    loading: Option<LoadHandle>,
+    preloads: Vec<LoadHandle>,
+    image_cache: ImageCache,
     image_directory: Option<ImageDirectory>,
     flip_horizontal: bool,
     flip_vertical: bool,
     rotation: usize,
     context: egui::Context,
     theme: Theme,
-    settings_screen: bool,
+    screen: Screen,
     settings: Settings,
     file_dialog: Option<FileDialogHandle>,
     color_analyzer: ColorAnalyzer,
+    svg_inspector_open: bool,
+    tone_mapping: ToneMapping,
+    tone_mapping_open: bool,
     image_info_panel: Option<ImageInfoState>,
+    clipboard: SystemClipboard,
+    // set while the region to copy is selected
+    region_selection: Option<RegionSelection>,
+    overrides: SessionOverrides,
+    slideshow: Option<Slideshow>,
+    // the image before the current one while the slideshow crossfades between them
+    fading_image: Option<OpenedImage>,
+    recent_files: RecentFiles,
+    thumbnails: Thumbnails,
+    // the gallery loads them when its tiles are bigger than the normal thumbnails
+    large_thumbnails: Option<Thumbnails>,
+    // set when the gallery is opened so it starts at the current image
+    gallery_scroll_to_current: bool,
+    // the zoom and pan of the last session, applied once its image is shown
+    restored_view: Option<(f32, egui::Vec2)>,
+    // the image that was opened from a dialog, a drop, the command line, a paste or the recent files
+    // it's added to the recent files once it's shown, the images that are browsed to aren't
+    opened_path: Option<PathBuf>,
+    // if the pointer was over the image in the last frame, the slideshow can pause while it is
+    image_hovered: bool,
+    filter_bar: FilterBar,
+    filter_bar_open: bool,
+    // the image the filmstrip last scrolled to
+    filmstrip_index: Option<usize>,
+    #[cfg(unix)]
+    control_server: Option<ipc::Server>,
     error: Option<ErrorWindow>
 }
 
 impl EnchantedView {
-    fn new(context: egui::Context) -> Self {
+    fn new(context: egui::Context, cli: Cli) -> Self {
         let settings = Settings::load(&context);
-        let theme = Theme::get(settings.theme.clone());
+        let overrides = cli.overrides();
+        let theme = Theme::get(overrides.theme.clone().unwrap_or_else(|| settings.theme.clone()));
         context.style_mut(|style| {
             style.interaction.tooltip_delay = 0.5;
             style.visuals = theme.visuals().clone();
             style.text_styles = theme.text_style();
         });
-        let texture_path = std::env::args_os().skip(1).next();
-        let (image, directory) = match texture_path {
-            Some(path_value) => {
-                let image_path = PathBuf::from(path_value.clone());
-                let load = context.load_texture_file(&image_path, TextureOptions {
-                    magnification: settings.image_filtering,
-                    minification: TextureFilter::Linear,
-                });
-                let directory = if load.as_ref().is_err_and(|error| {
-                    match error {
-                        ImageError::IoError(_) => false,
-                        _ => true
-                    }
-                }) || load.is_ok() {
-                    let path = image_path.canonicalize().expect("Couldn't find absolute path");
-                    Some(ImageDirectory::new(&path).expect("Unable to create the image directory"))
-                } else { None };
-                (image_or_error(load, &image_path, &theme), directory)
-            },
-            None => (Err(None), None),
+        let last_session = LastSession::load(&context).filter(|_| settings.restore_session && cli.paths.is_empty());
+        let opened = match last_session.as_ref() {
+            Some(session) => restore_session(session, &settings, &context),
+            None => open_paths(&cli.paths, cli.index, &settings, &context, &ToneMapping::default())
+        };
+        // the restored session was opened before, it's already in the recent files
+        let opened_path = loading_path(&opened).filter(|_| last_session.is_none());
+        let (image, loading, directory) = match opened {
+            Ok((loading, directory)) => (Err(None), loading, directory),
+            Err(error) => (Err(Some(error)), None, None)
         };
+        let image_cache = ImageCache::new(settings.cache_budget_bytes());
+        let recent_files = RecentFiles::load(&context);
+        let thumbnails = Thumbnails::new(&context, ThumbnailSize::Normal);
+        // the interval on the command line replaces the one in the settings
+        let slideshow = cli.slideshow.map(|interval| Slideshow::new(SlideshowOptions {
+            interval: interval.as_secs_f32(),
+            shuffle: cli.shuffle || settings.slideshow.shuffle,
+            ..settings.slideshow.clone()
+        }));
+        #[cfg(unix)]
+        let control_server = if cli.single_instance {
+            ipc::Server::start(&context)
+                .map_err(|error| eprintln!("Couldn't listen on {}: {error}", ipc::socket_path().display()))
+                .ok()
+        } else { None };
         Self {
             image,
+            loading,
+            preloads: Vec::new(),
+            image_cache,
             image_directory: directory,
-            flip_horizontal: false,
-            flip_vertical: false,
-            rotation: 0,
+            flip_horizontal: last_session.as_ref().is_some_and(|session| session.flip_horizontal),
+            flip_vertical: last_session.as_ref().is_some_and(|session| session.flip_vertical),
+            rotation: last_session.as_ref().map_or(0, |session| session.rotation),
             context,
             theme,
-            settings_screen: false,
+            screen: Screen::Main,
             settings,
             file_dialog: None,
             color_analyzer: ColorAnalyzer::new(),
+            svg_inspector_open: false,
+            tone_mapping: ToneMapping::default(),
+            tone_mapping_open: false,
             image_info_panel: None,
+            clipboard: SystemClipboard::default(),
+            region_selection: None,
+            overrides,
+            slideshow,
+            fading_image: None,
+            recent_files,
+            thumbnails,
+            large_thumbnails: None,
+            gallery_scroll_to_current: false,
+            filter_bar: FilterBar::default(),
+            filter_bar_open: false,
+            filmstrip_index: None,
+            restored_view: last_session.filter(|session| session.path.is_file()).map(|session| (session.scale, session.offset)),
+            opened_path,
+            image_hovered: false,
+            #[cfg(unix)]
+            control_server,
             error: None
         }
     }
     
-    fn load_image(&mut self, path: &PathBuf) {
-        let load = self.context.load_texture_file(path, TextureOptions {
-            magnification: self.settings.image_filtering,
-            minification: TextureFilter::Linear,
-        });
-        if let Some(info) = self.image_info_panel.as_mut() {
-            info.rename = None;
+    fn load_image(&mut self, path: &Path) {
+        let cached = CacheKey::for_file(path).and_then(|key| self.image_cache.get(&key).cloned());
+        if let Some(decoded) = cached {
+            if decoded.display_for(&self.tone_mapping).is_some() {
+                if let Some(info) = self.image_info_panel.as_mut() {
+                    info.rename = None;
+                }
+                // it was decoded ahead of time, only the texture upload is left
+                self.loading = None;
+                self.show_decoded(path, Ok(decoded));
+            }
+            else {
+                // the tone mapping changed since it was decoded
+                self.start_loading(ImageLoader::decoded(path, (*decoded).clone()).load(&self.context, &self.tone_mapping));
+            }
+        }
+        else if let Some(index) = self.preloads.iter().position(|handle| handle.path() == path) {
+            let handle = self.preloads.remove(index);
+            self.start_loading(handle);
+        }
+        else {
+            self.start_loading(ImageLoader::file(path).load(&self.context, &self.tone_mapping));
         }
-        self.image = image_or_error(load, path, &self.theme);
     }
 
-    fn load_image_raw(&mut self, bytes: &[u8], path: &PathBuf) {
-        let load = self.context.load_texture_raw(path, bytes, TextureOptions {
-            magnification: self.settings.image_filtering,
-            minification: TextureFilter::Linear,
-        });
+    fn load_image_raw(&mut self, bytes: Arc<[u8]>, path: &Path) {
+        self.start_loading(ImageLoader::bytes(path, bytes).load(&self.context, &self.tone_mapping));
+    }
+
+    fn start_loading(&mut self, handle: LoadHandle) {
         if let Some(info) = self.image_info_panel.as_mut() {
             info.rename = None;
         }
+        // replacing the previous handle cancels it, its decoding stops before the next stage or frame so quickly skipping through images doesn't pile up work
+        self.loading = Some(handle);
+        self.image = Err(None);
+    }
+
+    fn finish_loading(&mut self) {
+        let decoded = self.loading.as_ref().and_then(|handle| handle.image_decoded());
+        if let Some(decoded) = decoded {
+            let handle = self.loading.take().expect("The handle was checked above.");
+            let decoded = decoded.map(Arc::new);
+            if let (Ok(decoded), Some(modified)) = (decoded.as_ref(), handle.modified()) {
+                self.image_cache.insert(CacheKey::new(handle.path(), modified), decoded.clone());
+            }
+            self.show_decoded(handle.path(), decoded);
+        }
+        self.finish_preloading();
+    }
+
+    fn show_decoded(&mut self, path: &Path, decoded: ImageResult<Arc<DecodedImage>>) {
+        let options = self.texture_options();
+        let name = path.to_string_lossy().to_string();
+        let load = decoded.map(|decoded| self.context.load_texture_from_decoded(&decoded, &self.tone_mapping, options, name));
         self.image = image_or_error(load, path, &self.theme);
+        // only the first image that is shown gets the view, even if it failed to load
+        let restored_view = self.restored_view.take();
+        let opened = self.opened_path.take().is_some_and(|opened| opened == *path);
+        if let Ok(image) = self.image.as_mut() {
+            match (restored_view, self.overrides.zoom) {
+                (Some((scale, offset)), _) => image.display.request_view(scale, offset),
+                (None, Some(Zoom::Scale(scale))) => image.display.request_scale(scale),
+                _ => ()
+            }
+            // pasted images have no file to reopen
+            if opened && path.is_file() && self.recent_files.add(path) {
+                self.recent_files.store(&self.context);
+            }
+        }
+        self.preload_neighbours();
+    }
+
+    fn preload_neighbours(&mut self) {
+        let neighbours = self.image_directory.as_ref()
+            .map(|directory| directory.neighbours(self.settings.preload_count))
+            .unwrap_or_default();
+        // the user moved away from these images, no need to keep decoding them
+        self.preloads.retain(|handle| neighbours.contains(handle.path()));
+        for path in neighbours {
+            let cached = CacheKey::for_file(&path).is_some_and(|key| self.image_cache.contains(&key));
+            let loading = self.preloads.iter().any(|handle| *handle.path() == path);
+            if !cached && !loading {
+                self.preloads.push(ImageLoader::file(&path).load(&self.context, &self.tone_mapping));
+            }
+        }
+    }
+
+    fn finish_preloading(&mut self) {
+        let image_cache = &mut self.image_cache;
+        self.preloads.retain(|handle| {
+            match handle.image_decoded() {
+                Some(decoded) => {
+                    if let (Ok(decoded), Some(modified)) = (decoded, handle.modified()) {
+                        image_cache.insert(CacheKey::new(handle.path(), modified), Arc::new(decoded));
+                    }
+                    false
+                },
+                None => true
+            }
+        });
     }
 
+    fn texture_options(&self) -> TextureOptions {
+        let magnification = self.overrides.image_filtering.unwrap_or(self.settings.image_filtering);
+        TextureOptions { magnification, minification: TextureFilter::Linear }
+    }
+
+    /// Uploads the textures again with the current texture options.
     fn reload_texture(&mut self) {
+        let name = self.image_name();
+        let options = self.texture_options();
+        if let Ok(image) = &mut self.image {
+            image.display.texture_handle = TiledTexture::upload(&self.context, &image.tiles, options, &name);
+            let frame_textures = self.context.frame_textures(image.frames.clone(), image.color_profile.clone(), &self.tone_mapping, options, name);
+            image.display.set_frame_textures(frame_textures);
+            if let Some(svg) = image.svg.as_mut() {
+                svg.invalidate();
+            }
+        }
+    }
+
+    /// Converts the image for the display again on a worker, the texture is replaced once it's done.
+    fn update_display_image(&mut self) {
+        let name = self.image_name();
+        let options = self.texture_options();
         if let Ok(image) = &mut self.image {
-            let name = self.image_directory.as_ref().expect("The path info should be valid if the image is").image_name();
-            let options = TextureOptions { magnification: self.settings.image_filtering, minification: TextureFilter::Linear };
-            let handle = self.context.load_texture_from_image(&image.image, options, name);
-            image.display.texture_handle = handle;
+            image.pending_display = Some(DisplayHandle::start(&self.context, image.image.clone(), image.color_profile.clone(), &self.tone_mapping));
+            // the frames are converted when they are shown
+            let frame_textures = self.context.frame_textures(image.frames.clone(), image.color_profile.clone(), &self.tone_mapping, options, name);
+            image.display.set_frame_textures(frame_textures);
+        }
+    }
+
+    fn finish_display_image(&mut self) {
+        let name = self.image_name();
+        let options = self.texture_options();
+        let Ok(image) = &mut self.image else { return };
+        let Some(pending) = image.pending_display.as_ref() else { return };
+        match pending.try_recv() {
+            Ok(tiles) => {
+                let handle = TiledTexture::upload(&self.context, &tiles, options, name);
+                // pages can have different sizes, the view is only fitted again for those
+                if tiles.size() == image.tiles.size() {
+                    image.display.texture_handle = handle;
+                }
+                else {
+                    image.display.set_texture(handle, tiles.size());
+                }
+                image.tiles = tiles;
+                image.pending_display = None;
+                if let Some(svg) = image.svg.as_mut() {
+                    svg.invalidate();
+                }
+            },
+            Err(TryRecvError::Empty) => (),
+            // the old texture stays when the conversion failed
+            Err(TryRecvError::Disconnected) => image.pending_display = None
+        }
+    }
+
+    fn show_page(&mut self, page: usize) {
+        if let Ok(image) = &mut self.image {
+            if let Some(pages) = image.pages.as_mut() {
+                pages.switch(&mut image.image, page);
+            }
+        }
+        self.update_display_image();
+    }
+
+    fn image_name(&self) -> String {
+        self.image_directory.as_ref().map_or_else(|| String::from(PASTED_IMAGE_NAME), |directory| directory.image_name().clone())
+    }
+
+    fn paste_image(&mut self) {
+        match self.clipboard.paste_image() {
+            Ok(ClipboardImage::Image(image)) => {
+                self.image_directory = None;
+                self.loading = None;
+                if let Some(info) = self.image_info_panel.as_mut() {
+                    info.rename = None;
+                }
+                let decoded = DecodedImage { image: Arc::new(image), frames: Arc::default(), pages: None, inner_format: None, file_format: None, svg: None, photo_metadata: None, color_profile: None, display: None };
+                self.start_loading(ImageLoader::decoded(&PathBuf::new(), decoded).load(&self.context, &self.tone_mapping));
+            },
+            Ok(ClipboardImage::Bytes(bytes)) => {
+                self.image_directory = None;
+                // an empty path has no file metadata and no neighbours
+                self.load_image_raw(bytes.into(), &PathBuf::new());
+            },
+            Ok(ClipboardImage::Path(path)) => {
+                let path = path.canonicalize().unwrap_or(path);
+                self.opened_path = Some(path.clone());
+                self.browse_directory_of(&path);
+                self.load_image(&path);
+            },
+            Err(error) => {
+                self.error = Some(ErrorWindow { 
+                    title: String::from("Paste Failed"), 
+                    description: error
+                });
+            }
+        }
+    }
+
+    /// Copies the shown image with the current frame, rotation, flipping and tone mapping, or only the `region` of it in texture pixels.
+    fn copy_image(&mut self, region: Option<egui::Rect>) {
+        let Ok(image) = self.image.as_ref() else { return };
+        let frame = image.display.animation.as_ref().and_then(|animation| image.frames.get(animation.current_frame()));
+        let source = frame.map_or(image.image.as_ref(), |frame| &frame.image);
+        let cropped;
+        let source = match region {
+            Some(region) => {
+                let bounds = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(source.width() as f32, source.height() as f32));
+                let region = region.intersect(bounds);
+                let (min, max) = (region.min.floor(), region.max.ceil());
+                // a click without a drag selects nothing
+                if max.x <= min.x || max.y <= min.y {
+                    return;
+                }
+                cropped = source.crop_imm(min.x as u32, min.y as u32, (max.x - min.x) as u32, (max.y - min.y) as u32);
+                &cropped
+            },
+            None => source
+        };
+        let mut rgba = if ToneMapping::applies_to(source) {
+            self.tone_mapping.apply(&source.to_rgba32f())
+        }
+        else {
+            source.to_rgba8()
+        };
+        if let Some(profile) = image.color_profile.as_ref() {
+            profile.to_display(&mut rgba);
+        }
+        let rgba = transform_image(&DynamicImage::ImageRgba8(rgba), self.rotation, self.flip_horizontal, self.flip_vertical).into_rgba8();
+        if let Err(error) = self.clipboard.copy_image(&rgba) {
+            self.error = Some(ErrorWindow { 
+                title: String::from("Copy Failed"), 
+                description: format!("Couldn't copy the image to the clipboard.\n{error}")
+            });
+        }
+    }
+
+    fn copy_file(&mut self, path_only: bool) {
+        let Some(directory) = self.image_directory.as_ref() else { return };
+        let path = directory.current_image_path();
+        let result = if path_only {
+            self.clipboard.copy_text(&path.to_string_lossy())
+        }
+        else {
+            self.clipboard.copy_file(path)
+        };
+        if let Err(error) = result {
+            self.error = Some(ErrorWindow { 
+                title: String::from("Copy Failed"), 
+                description: format!("Couldn't copy '{}' to the clipboard.\n{error}", directory.image_name())
+            });
+        }
+    }
+
+    /// Browses the directory with the current filter.
+    fn set_image_directory(&mut self, mut directory: Option<ImageDirectory>) {
+        if let Some(directory) = directory.as_mut().filter(|_| self.filter_bar.filter.is_active()) {
+            directory.set_filter(self.filter_bar.filter.clone());
+        }
+        self.image_directory = directory;
+    }
+
+    /// Browses the images next to the one at `path`, it's shown on its own if that fails.
+    fn browse_directory_of(&mut self, path: &PathBuf) {
+        match ImageDirectory::new(path, self.settings.sort_order, self.settings.include_subfolders) {
+            Ok(directory) => self.set_image_directory(Some(directory)),
+            Err(error) => {
+                self.image_directory = None;
+                self.error = Some(ErrorWindow { 
+                    title: String::from("Browsing Failed"), 
+                    description: format!("Couldn't browse the other images in the folder.\n{error}")
+                });
+            }
+        }
+    }
+
+    fn open_paths(&mut self, paths: &[PathBuf]) -> Result<(), String> {
+        let opened = open_paths(paths, None, &self.settings, &self.context, &self.tone_mapping);
+        self.opened_path = loading_path(&opened);
+        let (loading, directory) = opened?;
+        self.set_image_directory(directory);
+        if let Some(handle) = loading {
+            self.start_loading(handle);
+        }
+        Ok(())
+    }
+
+    /// Answers the commands that were sent to the control socket.
+    #[cfg(unix)]
+    fn handle_control_requests(&mut self, frame: &mut eframe::Frame) {
+        while let Some(request) = self.control_server.as_ref().and_then(|server| server.try_recv()) {
+            let result = match &request.command {
+                ipc::Command::Open(paths) => {
+                    frame.focus();
+                    self.open_paths(paths).map(|_| None)
+                },
+                ipc::Command::Next => {
+                    self.next_image();
+                    Ok(None)
+                },
+                ipc::Command::Previous => {
+                    self.previous_image();
+                    Ok(None)
+                },
+                ipc::Command::Rotate => {
+                    self.rotation = (self.rotation + 1) % 4;
+                    Ok(None)
+                },
+                ipc::Command::Zoom(_) | ipc::Command::ZoomIn | ipc::Command::ZoomOut => match self.image.as_mut() {
+                    Ok(image) => {
+                        match &request.command {
+                            ipc::Command::Zoom(Zoom::Fit) => image.display.zoom_to_fit(),
+                            ipc::Command::Zoom(Zoom::Scale(scale)) => image.display.request_scale(*scale),
+                            ipc::Command::ZoomIn => image.display.zoom_in(),
+                            _ => image.display.zoom_out()
+                        }
+                        Ok(None)
+                    },
+                    Err(_) => Err(String::from("no image is open"))
+                },
+                ipc::Command::Quit => {
+                    frame.close();
+                    Ok(None)
+                },
+                ipc::Command::Current => self.image_directory.as_ref()
+                    .map(|directory| Some(directory.current_image_path().to_string_lossy().to_string()))
+                    .ok_or_else(|| String::from("no image file is open"))
+            };
+            request.reply(result);
+        }
+    }
+
+    /// Remembers the open image so it can be reopened on the next start.
+    fn store_session(&self, ctx: &egui::Context) {
+        if let (Ok(image), Some(directory)) = (self.image.as_ref(), self.image_directory.as_ref()) {
+            let session = LastSession {
+                path: directory.current_image_path().clone(),
+                index: directory.image_index(),
+                scale: image.display.scale,
+                offset: image.display.offset(),
+                rotation: self.rotation,
+                flip_horizontal: self.flip_horizontal,
+                flip_vertical: self.flip_vertical
+            };
+            if LastSession::load(ctx).as_ref() != Some(&session) {
+                session.store(ctx);
+            }
+        }
+    }
+
+    fn open_recent_file(&mut self, path: &PathBuf) {
+        if !path.is_file() {
+            self.recent_files.remove(path);
+            self.recent_files.store(&self.context);
+            self.error = Some(ErrorWindow { 
+                title: String::from("Open Failed"), 
+                description: format!("'{}' doesn't exist anymore.", format_path(path))
+            });
+            return;
+        }
+        if let Err(error) = self.open_paths(std::slice::from_ref(path)) {
+            self.error = Some(ErrorWindow { 
+                title: String::from("Open Failed"), 
+                description: error
+            });
         }
     }
 
@@ -226,14 +736,66 @@ impl EnchantedView {
         }
     }
 
+    fn toggle_slideshow(&mut self) {
+        if self.slideshow.take().is_none() && self.image_directory.is_some() {
+            self.slideshow = Some(Slideshow::new(self.settings.slideshow.clone()));
+        }
+        self.fading_image = None;
+    }
+
+    fn advance_slideshow(&mut self) {
+        let Some(slideshow) = self.slideshow.as_mut() else { return };
+        let Some(mut directory) = self.image_directory.take() else {
+            self.slideshow = None;
+            return;
+        };
+        let previous = directory.current_image_path().clone();
+        match slideshow.next(&mut directory) {
+            // a single image stays on screen
+            Some(path) if path == previous => (),
+            Some(path) => {
+                if slideshow.transition() == Transition::Crossfade {
+                    // an image that was still fading in is shown fully while it fades out
+                    self.fading_image = std::mem::replace(&mut self.image, Err(None)).ok().map(|mut image| {
+                        image.display.opacity = 1.0;
+                        image
+                    });
+                }
+                self.load_image(&path);
+            },
+            // every image was shown once
+            None => self.slideshow = None
+        }
+        self.image_directory = Some(directory);
+    }
+
     fn hotkeys(&mut self, ctx: &egui::Context) {
         if ctx.output(|output| output.text_cursor_pos.is_none()) {
+            if let Some(slideshow) = self.slideshow.as_mut() {
+                if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.pause_slideshow)) {
+                    slideshow.paused = !slideshow.paused;
+                }
+            }
+            if self.region_selection.is_some() && ctx.input_mut(|input| input.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+                self.region_selection = None;
+            }
+            // the bars are hidden while the slideshow runs, escape always stops it
+            let escape = self.slideshow.is_some() && ctx.input_mut(|input| input.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+            if escape || ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.toggle_slideshow)) {
+                self.toggle_slideshow();
+            }
+            if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.paste_image)) {
+                self.paste_image();
+            }
             if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.next_image)) {
                 self.next_image();
             }
             if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.previous_image)) {
                 self.previous_image();
             }
+            if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.copy_image)) {
+                self.copy_image(None);
+            }
             if let Ok(image) = &mut self.image {
                 if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.zoom_in)) {
                     image.display.zoom_in();
@@ -256,6 +818,19 @@ impl EnchantedView {
                 if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.flip_vertical)) {
                     self.flip_vertical = !self.flip_vertical;
                 }
+                if let Some(animation) = image.display.animation.as_mut() {
+                    if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.toggle_playback)) {
+                        animation.toggle_playback();
+                    }
+                    if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.next_frame)) {
+                        animation.playing = false;
+                        animation.next_frame();
+                    }
+                    if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.previous_frame)) {
+                        animation.playing = false;
+                        animation.previous_frame();
+                    }
+                }
                 if self.settings.experimental_features {
                     if ctx.input_mut(|input| input.consume_shortcut(&self.settings.key_binds.pick_color)) {
                         self.color_analyzer.toggle_color_picker();
@@ -266,7 +841,7 @@ impl EnchantedView {
     }
 
     fn update_theme(&mut self) {
-        self.theme = Theme::get(self.settings.theme.clone());
+        self.theme = Theme::get(self.overrides.theme.clone().unwrap_or_else(|| self.settings.theme.clone()));
         self.context.style_mut(|style| {
             style.visuals = self.theme.visuals().clone();
             style.text_styles = self.theme.text_style();
@@ -277,9 +852,12 @@ impl EnchantedView {
     }
 
     fn check_for_image_updates(&mut self) {
+        self.finish_loading();
+        self.finish_display_image();
         if let Some(handle) = self.file_dialog.as_ref() {
             if let Some(path) = handle.file_picked() {
-                self.image_directory = Some(ImageDirectory::new(&path).expect("Unable to initialize the image directory."));
+                self.opened_path = Some(path.clone());
+                self.browse_directory_of(&path);
                 self.load_image(&path);
             }
         }
@@ -291,9 +869,22 @@ impl EnchantedView {
                     },
                     image_directory::Change::Rename => {
                         if let Ok(image) = self.image.as_mut() {
-                            image.metadata.file_format = image::ImageFormat::from_path(directory.current_image_path()).ok();
+                            image.metadata.file_format = FileFormat::from_path(directory.current_image_path()).ok();
                         }
                     },
+                    image_directory::Change::WatchError(description) => {
+                        self.error = Some(ErrorWindow { 
+                            title: String::from("Watching Failed"), 
+                            description
+                        });
+                    },
+                    image_directory::Change::Empty => {
+                        // there is nothing left to browse or show
+                        self.slideshow = None;
+                        self.loading = None;
+                        self.image = Err(None);
+                        return;
+                    }
                 }
             }
             self.image_directory = Some(directory);
@@ -306,11 +897,29 @@ impl EnchantedView {
     fn main_screen(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
         let previous_spacing = ui.spacing().item_spacing;
         ui.spacing_mut().item_spacing.y = 0.0;
-        self.toolbar(ui);
-        ui.separator(); 
-        ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
-            self.bottom_bar(ui);  
+        // the slideshow only shows the image
+        let show_bars = self.slideshow.is_none();
+        if show_bars {
+            self.toolbar(ui);
             ui.separator(); 
+        }
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+            if show_bars {
+                self.bottom_bar(ui);  
+                ui.separator(); 
+                if self.settings.show_filmstrip && self.image_directory.is_some() {
+                    self.filmstrip(ui);
+                    ui.separator();
+                }
+                if self.image.as_ref().is_ok_and(|image| image.display.animation.is_some()) {
+                    self.animation_bar(ui);
+                    ui.separator();
+                }
+                if self.filter_bar_open {
+                    self.filter_bar(ui);
+                    ui.separator();
+                }
+            }
             ui.spacing_mut().item_spacing = previous_spacing;
             egui::SidePanel::left("image_info")
                 .resizable(true)
@@ -328,16 +937,110 @@ impl EnchantedView {
                 .show_animated_inside(ui, self.color_analyzer.is_open(), |ui| {
                     self.color_analyzer.ui(ui, self.image.is_ok(), &self.theme, &self.settings);
                 });
+            let is_svg = self.image.as_ref().is_ok_and(|image| image.svg.is_some());
+            egui::SidePanel::right("svg_inspector")
+                .resizable(true)
+                .show_separator_line(true)
+                .width_range(200.0..=400.0)
+                .default_width(250.0)
+                .show_animated_inside(ui, self.svg_inspector_open && is_svg, |ui| {
+                    self.svg_inspector(ui);
+                });
+            let is_hdr = self.image.as_ref().is_ok_and(|image| ToneMapping::applies_to(&image.image));
+            egui::SidePanel::right("tone_mapping")
+                .resizable(true)
+                .show_separator_line(true)
+                .width_range(200.0..=400.0)
+                .default_width(250.0)
+                .show_animated_inside(ui, self.tone_mapping_open && is_hdr, |ui| {
+                    self.tone_mapping_panel(ui);
+                });
             egui::CentralPanel::default().frame(egui::Frame::central_panel(ui.style()).inner_margin(0.0)).show_inside(ui, |ui| {
+                let texture_options = self.texture_options();
+                let mut open_recent = None;
+                let mut copy_region = None;
                 let res =  match &mut self.image {
+                    // the slideshow keeps the previous image until the next one is decoded
+                    _ if self.loading.is_some() && self.fading_image.is_some() => {
+                        let previous = self.fading_image.as_mut().expect("The fading image was checked above.");
+                        previous.display.update(ui, self.flip_horizontal, self.flip_vertical, self.rotation, false)
+                    },
+                    _ if self.loading.is_some() => {
+                        let name = self.loading.as_ref()
+                            .and_then(|handle| handle.path().file_name())
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        CenterContainer::new(ui.available_size()).inner_layout(egui::Layout::top_down(egui::Align::Center)).ui(ui, |ui| {
+                            ui.spacing_mut().item_spacing = egui::vec2(10.0, 10.0);
+                            ui.add(egui::Spinner::new().size(32.0));
+                            ui.label(egui::RichText::new(format!("Loading {name}")).text_style(self.theme.heading3()));
+                        }).response
+                    },
                     Ok(opened_image) => {
+                        let opacity = match (self.fading_image.as_mut(), self.slideshow.as_mut()) {
+                            (Some(previous), Some(slideshow)) => {
+                                let mut previous_ui = ui.child_ui_with_id_source(ui.available_rect_before_wrap(), *ui.layout(), "crossfade");
+                                previous.display.update(&mut previous_ui, self.flip_horizontal, self.flip_vertical, self.rotation, false);
+                                slideshow.fade_in(ui.ctx())
+                            },
+                            _ => 1.0
+                        };
+                        if opacity >= 1.0 {
+                            self.fading_image = None;
+                        }
+                        opened_image.display.opacity = opacity;
                         let highlight_pixel = self.color_analyzer.is_picking_color();
+                        opened_image.display.drag_to_pan = self.region_selection.is_none();
                         let res = opened_image.display.update(ui, self.flip_horizontal, self.flip_vertical, self.rotation, highlight_pixel);
+                        self.image_hovered = res.hovered();
+                        if let Some(svg) = opened_image.svg.as_mut() {
+                            let base_scale = svg.document().base_scale();
+                            // rendering at the screen resolution keeps the edges sharp at every zoom level
+                            let scale = opened_image.display.scale * base_scale * ui.ctx().pixels_per_point();
+                            if let Some(rendered) = svg.update(ui.ctx(), scale) {
+                                let name = opened_image.display.texture_handle.name().to_owned();
+                                opened_image.display.texture_handle = TiledTexture::load(ui.ctx(), rendered, texture_options, name);
+                            }
+                            if let Some(bounds) = svg.highlighted {
+                                let bounds = egui::Rect::from_min_max((bounds.min.to_vec2() * base_scale).to_pos2(), (bounds.max.to_vec2() * base_scale).to_pos2());
+                                let rect = opened_image.display.image_rect_to_screen(bounds, self.flip_horizontal, self.flip_vertical, self.rotation);
+                                let selection = ui.visuals().selection;
+                                ui.painter().rect(rect, 0.0, selection.bg_fill.gamma_multiply(0.3), selection.stroke);
+                            }
+                        }
+                        if let Some(selection) = self.region_selection.as_mut() {
+                            if res.hovered() {
+                                ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+                            }
+                            let pointer = res.interact_pointer_pos()
+                                .map(|pos| opened_image.display.screen_to_image(pos, self.flip_horizontal, self.flip_vertical, self.rotation));
+                            if let Some(pointer) = pointer {
+                                if res.drag_started() {
+                                    selection.start = Some(pointer);
+                                }
+                                selection.end = pointer;
+                            }
+                            if let Some(start) = selection.start {
+                                let region = egui::Rect::from_two_pos(start, selection.end);
+                                let rect = opened_image.display.image_rect_to_screen(region, self.flip_horizontal, self.flip_vertical, self.rotation);
+                                let selection = ui.visuals().selection;
+                                ui.painter().rect(rect, 0.0, selection.bg_fill.gamma_multiply(0.3), selection.stroke);
+                                if res.drag_released() {
+                                    copy_region = Some(region);
+                                }
+                            }
+                        }
                         if res.clicked() && highlight_pixel {
                             let hover_pos = res.hover_pos().expect("There must be a hover pos.");
                             let (x, y) = opened_image.display.get_image_pixel_coords(hover_pos.to_vec2());
-                            let pixel = opened_image.image.get_pixel(x, y).0;
-                            self.color_analyzer.set_color(egui::Color32::from_rgba_unmultiplied(pixel[0], pixel[1], pixel[2], pixel[3]));
+                            let image = opened_image.current_image();
+                            let pixel = self.tone_mapping.display_pixel(image, x, y);
+                            let pixel = opened_image.color_profile.as_ref().map_or(pixel, |profile| profile.to_display_color(pixel));
+                            let sample = PixelSample::read(image, x, y).with_exposure(self.tone_mapping.exposure);
+                            let converted = opened_image.color_profile.as_ref().is_some_and(|profile| profile.converts());
+                            // the source values are only interesting if they are different from the shown color
+                            let source = Some(sample).filter(|sample| sample.is_high_bit_depth() || converted);
+                            self.color_analyzer.set_color(egui::Color32::from_rgba_unmultiplied(pixel[0], pixel[1], pixel[2], pixel[3]), source);
                         }
                         res
                     },
@@ -350,15 +1053,32 @@ impl EnchantedView {
                             }
                             if ui.add(Button::new(egui::RichText::new("Open an image").text_style(self.theme.heading3()))).clicked() {
                                 let start_dir = self.image_directory.as_ref().and_then(|directory| Some(directory.current_directory_path()));
-                                let formats = ImageFormat::iterator().flat_map(|format| format.extensions_str());
+                                let formats = FileFormat::iterator().filter(|format| format.can_read()).flat_map(|format| format.extensions_str());
                                 self.file_dialog = Some(FileDialog::new(frame).title("Choose an image").directory(start_dir).add_filter("Image Formats", &formats.collect::<Vec<&&str>>()).pick_file(ui.ctx()));
                             }
                             ui.label(egui::RichText::new("or").text_style(self.theme.heading3()));
                             ui.label(egui::RichText::new("drag an image to the window.").text_style(self.theme.heading3()));
+                            let paste = format!("You can also paste one ({}).", ui.ctx().format_shortcut(&self.settings.key_binds.paste_image));
+                            ui.label(egui::RichText::new(paste).color(ui.visuals().weak_text_color()));
+                            open_recent = recent_files_grid(ui, self.recent_files.paths(), &mut self.thumbnails, &self.theme);
                         }).response
                     }
                 };
                 self.handle_drop_files(ui, res.rect);
+                if self.slideshow.as_ref().is_some_and(|slideshow| slideshow.paused) {
+                    let text = format!("Slideshow paused ({})", ui.ctx().format_shortcut(&self.settings.key_binds.pause_slideshow));
+                    let galley = ui.painter().layout_no_wrap(text, egui::TextStyle::Body.resolve(ui.style()), ui.visuals().strong_text_color());
+                    let rect = egui::Align2::CENTER_TOP.anchor_rect(egui::Rect::from_min_size(res.rect.center_top() + egui::vec2(0.0, 10.0), galley.size())).expand(6.0);
+                    ui.painter().rect_filled(rect, ui.visuals().window_rounding, ui.visuals().window_fill);
+                    ui.painter().galley(rect.shrink(6.0).min, galley);
+                }
+                if let Some(path) = open_recent {
+                    self.open_recent_file(&path);
+                }
+                if let Some(region) = copy_region {
+                    self.region_selection = None;
+                    self.copy_image(Some(region));
+                }
             });
         });
         self.hotkeys(ui.ctx());
@@ -371,6 +1091,9 @@ impl EnchantedView {
             self.theme.style_image_button(ui);
             CenterContainer::new(toolbar_size).inner_layout(toolbar_layout).ui(ui, |ui| {
                 self.zoom_control(ui);
+                if self.image.as_ref().is_ok_and(|image| image.pages.is_some()) {
+                    self.page_control(ui);
+                }
                 self.flip_control(ui);
                 self.rotate_control(ui);
             });
@@ -378,13 +1101,30 @@ impl EnchantedView {
                 let settings_button = ImageButton::new(egui::include_image!("../assets/settings.png"))
                     .tint(self.theme.image_button().color);
                 if settings_button.ui(ui).clicked() {
-                    self.settings_screen = true;
+                    self.screen = Screen::Settings;
+                }
+                let gallery_button = ImageButton::new(egui::include_image!("../assets/gallery.png"))
+                    .tint(self.theme.image_button().color)
+                    .disabled_tint(self.theme.image_button().disabled_color)
+                    .enabled(self.image_directory.is_some())
+                    .tooltip("Gallery");
+                if gallery_button.ui(ui).clicked() {
+                    self.screen = Screen::Gallery;
+                    self.gallery_scroll_to_current = true;
                 }
 
                 if self.settings.experimental_features {
                     self.color_analyzer_control(ui);
                 }
                 self.image_info_control(ui);
+                self.copy_control(ui);
+                self.filter_control(ui);
+                if self.image.as_ref().is_ok_and(|image| image.svg.is_some()) {
+                    self.svg_inspector_control(ui);
+                }
+                if self.image.as_ref().is_ok_and(|image| ToneMapping::applies_to(&image.image)) {
+                    self.tone_mapping_control(ui);
+                }
             });
         });
     }
@@ -437,6 +1177,31 @@ impl EnchantedView {
         }
     }
 
+    fn page_control(&mut self, ui: &mut egui::Ui) {
+        let Some((current, count)) = self.image.as_ref().ok()
+            .and_then(|image| image.pages.as_ref())
+            .map(|pages| (pages.current(), pages.len())) else {
+            return;
+        };
+        let previous_button = ImageButton::new(egui::include_image!("../assets/arrow_left.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .enabled(current > 0)
+            .tooltip("Previous page");
+        if previous_button.ui(ui).clicked() {
+            self.show_page(current - 1);
+        }
+        ui.label(format!("{}/{}", current + 1, count));
+        let next_button = ImageButton::new(egui::include_image!("../assets/arrow_right.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .enabled(current + 1 < count)
+            .tooltip("Next page");
+        if next_button.ui(ui).clicked() {
+            self.show_page(current + 1);
+        }
+    }
+
     fn flip_control(&mut self, ui: &mut egui::Ui) {
         let flip_horizontal_button = ImageButton::new(egui::include_image!("../assets/flip_horizontal.png"))
             .tint(self.theme.image_button().color)
@@ -476,6 +1241,68 @@ impl EnchantedView {
         }
     }
 
+    fn filter_control(&mut self, ui: &mut egui::Ui) {
+        let filter_button = ImageButton::new(egui::include_image!("../assets/filter.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .selected(self.filter_bar_open)
+            .tooltip("Filter images");
+        if filter_button.ui(ui).clicked() {
+            self.filter_bar_open = !self.filter_bar_open;
+        }
+    }
+
+    fn filter_bar(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(5.0);
+        let changed = ui.horizontal(|ui| {
+            ui.add_space(5.0);
+            self.filter_bar.ui(ui)
+        }).inner;
+        ui.add_space(5.0);
+        if !changed {
+            return;
+        }
+        let Some(mut directory) = self.image_directory.take() else { return };
+        directory.set_filter(self.filter_bar.filter.clone());
+        // the shown image was filtered out, the next one that matches is shown instead
+        if directory.filtered_index().is_none() && directory.count() > 0 {
+            let path = directory.next_image();
+            self.load_image(path);
+        }
+        self.image_directory = Some(directory);
+        self.preload_neighbours();
+    }
+
+    fn copy_control(&mut self, ui: &mut egui::Ui) {
+        let copy_button = ImageButton::new(egui::include_image!("../assets/copy.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .enabled(self.image.is_ok())
+            .tooltip("Copy");
+        let res = copy_button.ui(ui);
+        let popup_id = ui.make_persistent_id("copy_menu");
+        if res.clicked() {
+            ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+        }
+        egui::popup::popup_below_widget(ui, popup_id, &res, |ui| {
+            ui.set_min_width(170.0);
+            if ui.add(Button::new("Copy image").shortcut_text(ui.ctx().format_shortcut(&self.settings.key_binds.copy_image))).clicked() {
+                self.copy_image(None);
+            }
+            if ui.add(Button::new("Copy region")).on_hover_text("Drag over the image to select the region, escape cancels.").clicked() {
+                self.region_selection = Some(RegionSelection::default());
+            }
+            // pasted images aren't files
+            let has_file = self.image_directory.is_some();
+            if ui.add_enabled(has_file, Button::new("Copy file path")).clicked() {
+                self.copy_file(true);
+            }
+            if ui.add_enabled(has_file, Button::new("Copy file")).clicked() {
+                self.copy_file(false);
+            }
+        });
+    }
+
     fn color_analyzer_control(&mut self, ui: &mut egui::Ui) {
         let color_analyzer_button = ImageButton::new(egui::include_image!("../assets/color.png"))
             .tint(self.theme.image_button().color)
@@ -488,6 +1315,28 @@ impl EnchantedView {
         }
     }
 
+    fn svg_inspector_control(&mut self, ui: &mut egui::Ui) {
+        let svg_inspector_button = ImageButton::new(egui::include_image!("../assets/svg_tree.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .selected(self.svg_inspector_open)
+            .tooltip("SVG Elements");
+        if svg_inspector_button.ui(ui).clicked() {
+            self.svg_inspector_open = !self.svg_inspector_open;
+        }
+    }
+
+    fn tone_mapping_control(&mut self, ui: &mut egui::Ui) {
+        let tone_mapping_button = ImageButton::new(egui::include_image!("../assets/exposure.png"))
+            .tint(self.theme.image_button().color)
+            .disabled_tint(self.theme.image_button().disabled_color)
+            .selected(self.tone_mapping_open)
+            .tooltip("Exposure");
+        if tone_mapping_button.ui(ui).clicked() {
+            self.tone_mapping_open = !self.tone_mapping_open;
+        }
+    }
+
     fn image_info_control(&mut self, ui: &mut egui::Ui) {
         let image_info_button = ImageButton::new(egui::include_image!("../assets/info.png"))
             .tint(self.theme.image_button().color)
@@ -503,7 +1352,7 @@ impl EnchantedView {
             else {
                 let current_format = self.image.as_ref().ok().and_then(|image| image.metadata.inner_format);
                 let format = image::ImageFormat::iterator().find_map(|format| {
-                    if format.can_write() && current_format.is_some_and(|f| f != *format) || current_format.is_none() {
+                    if format.can_write() && current_format.is_some_and(|f| f != FileFormat::Raster(*format)) || current_format.is_none() {
                         Some(*format)
                     } else { None }
                 }).expect("There should be atleast one format");
@@ -512,8 +1361,18 @@ impl EnchantedView {
                     selected_format: format,
                     format_ext: format.extensions_str()[0].to_owned(),
                     path_picker_state: PathPickerState::default(),
-                    target_path: self.image_directory.as_ref().and_then(|directory| Some(directory.current_directory_path())).unwrap_or(PathBuf::default()),
-                    target_name: self.image_directory.as_ref().and_then(|directory| Some(directory.image_name_stem())).unwrap_or_default().to_owned()
+                    target_path: self.image_directory.as_ref().map(|directory| directory.current_directory_path())
+                        .or_else(|| std::env::current_dir().ok())
+                        .unwrap_or_default(),
+                    target_name: self.image_directory.as_ref().map(|directory| directory.image_name_stem()).unwrap_or(PASTED_IMAGE_NAME).to_owned(),
+                    frame_export: FrameExportState {
+                        selection: FrameSelection::Current,
+                        range_start: 1,
+                        range_end: 1,
+                        frame_count: 0,
+                        name_pattern: String::from(DEFAULT_FRAME_NAME_PATTERN),
+                        exported: None
+                    }
                 });
             }
         }
@@ -524,6 +1383,68 @@ impl EnchantedView {
         }
     }
 
+    fn svg_inspector(&mut self, ui: &mut egui::Ui) {
+        ui.allocate_ui_with_layout(egui::vec2(ui.available_width(), self.theme.heading3().resolve(ui.style()).size), egui::Layout::left_to_right(egui::Align::Min), |ui|{
+            if close_button(ui).clicked() {
+                self.svg_inspector_open = false;
+            }
+            ui.label(egui::RichText::new("SVG Elements").text_style(self.theme.heading3()));
+        });
+        ui.add_space(ui.spacing().item_spacing.y);
+        if let Some(svg) = self.image.as_mut().ok().and_then(|image| image.svg.as_mut()) {
+            let mut highlighted = None;
+            egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                svg_element_tree(ui, svg.document().root(), egui::Id::new("svg_element_tree"), 0, &mut highlighted);
+            });
+            svg.highlighted = highlighted;
+        }
+    }
+
+    fn tone_mapping_panel(&mut self, ui: &mut egui::Ui) {
+        ui.allocate_ui_with_layout(egui::vec2(ui.available_width(), self.theme.heading3().resolve(ui.style()).size), egui::Layout::left_to_right(egui::Align::Min), |ui|{
+            if close_button(ui).clicked() {
+                self.tone_mapping_open = false;
+            }
+            ui.label(egui::RichText::new("Exposure").text_style(self.theme.heading3()));
+        });
+        ui.add_space(ui.spacing().item_spacing.y);
+        let previous = self.tone_mapping;
+        let mut sliders = Vec::new();
+        egui::Grid::new("tone_mapping_grid")
+            .num_columns(2)
+            .spacing(ui.spacing().item_spacing * egui::vec2(2.0, 1.5))
+            .show(ui, |ui| {
+                ui.label("Exposure");
+                sliders.push(ui.add(egui::Slider::new(&mut self.tone_mapping.exposure, -10.0..=10.0).step_by(0.1).suffix(" EV")));
+                ui.end_row();
+
+                ui.label("Tone mapping");
+                ComboBox::from_id_source("tone_map_operator")
+                    .selected_text(self.tone_mapping.operator.name())
+                    .show_ui(ui, |ui| {
+                        for operator in ToneMapOperator::iterator() {
+                            ui.selectable_value(&mut self.tone_mapping.operator, operator, operator.name());
+                        }
+                        ((), false)
+                    });
+                ui.end_row();
+
+                ui.label("Gamma");
+                sliders.push(ui.add(egui::Slider::new(&mut self.tone_mapping.gamma, 1.0..=3.0).step_by(0.1)));
+                ui.end_row();
+            });
+        if ui.add_enabled(self.tone_mapping != ToneMapping::default(), egui::Button::new("Reset")).clicked() {
+            self.tone_mapping = ToneMapping::default();
+        }
+        // the texture is made from the source image again, it's never changed by the tone mapping
+        // that's too slow for every step of a slider, so it waits until the slider is let go
+        let dragging = sliders.iter().any(|slider| slider.dragged());
+        let released = sliders.iter().any(|slider| slider.drag_released());
+        if (self.tone_mapping != previous && !dragging) || released {
+            self.update_display_image();
+        }
+    }
+
     fn image_info_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
         ui.allocate_ui_with_layout(egui::vec2(ui.available_width(), self.theme.heading3().resolve(ui.style()).size), egui::Layout::left_to_right(egui::Align::Min), |ui|{
             ui.label(egui::RichText::new("Image Info").text_style(self.theme.heading3()));
@@ -548,6 +1469,17 @@ impl EnchantedView {
                     ui.label(format!("{}x{}", image.image.width(), image.image.height()));
                     ui.end_row();
 
+                    if let Some(pages) = image.pages.as_ref() {
+                        ui.label("Page");
+                        ui.label(format!("{} of {}", pages.current() + 1, pages.len()));
+                        ui.end_row();
+
+                        ui.label("Page sizes");
+                        let sizes: Vec<String> = pages.sizes().iter().map(|(width, height)| format!("{width}x{height}")).collect();
+                        ui.add(egui::Label::new(sizes.join(", ")).wrap(true));
+                        ui.end_row();
+                    }
+
                     ui.label("size");
                     ui.label(format_bytes(image.metadata.file_size));
                     ui.end_row();
@@ -616,6 +1548,10 @@ impl EnchantedView {
                     ui.label("Format");
                     ui.label(image.metadata.image_format_display());
                     ui.end_row();
+
+                    ui.label("Color profile");
+                    ui.label(image.color_profile.as_ref().map_or("--", |profile| profile.name.as_str()));
+                    ui.end_row();
                 });
             if image.metadata.image_format_different() {
                 let text = format!("Image formats don't match. File extension is {} but the image format is {}.", image.metadata.file_format_display(), image.metadata.inner_format_display());
@@ -643,11 +1579,61 @@ impl EnchantedView {
                     }
                 }
             }
+            if let Some(photo_metadata) = image.metadata.photo_metadata.as_ref() {
+                ui.add_space(ui.spacing().item_spacing.y);
+                if let Some(error) = self.image_info_photo(ui, photo_metadata) {
+                    self.error = Some(error);
+                }
+            }
         }
         ui.add_space(100.0);
         self.image_info_conversion(ui, frame);
     }
 
+    fn image_info_photo(&self, ui: &mut egui::Ui, photo_metadata: &PhotoMetadata) -> Option<ErrorWindow> {
+        egui::Grid::new("photo_info_grid")
+            .num_columns(2)
+            .striped(true)
+            .spacing(ui.spacing().item_spacing * egui::vec2(2.0, 1.5))
+            .show(ui, |ui| {
+                let row = |ui: &mut egui::Ui, name: &str, value: Option<String>| {
+                    ui.label(name);
+                    ui.label(value.unwrap_or_else(|| String::from("--")));
+                    ui.end_row();
+                };
+                row(ui, "Camera", photo_metadata.camera());
+                row(ui, "Lens", photo_metadata.lens.clone());
+                row(ui, "Exposure", photo_metadata.exposure());
+                row(ui, "Focal length", photo_metadata.focal_length.map(|length| format!("{length:.0}mm")));
+                row(ui, "ISO", photo_metadata.iso.map(|iso| iso.to_string()));
+                row(ui, "Date taken", photo_metadata.date_taken.map(|date| format!("{}", date.format("%x %X"))));
+
+                ui.label("Location");
+                let mut error = None;
+                if let Some((latitude, longitude)) = photo_metadata.location {
+                    let text = format!("{latitude:.5}, {longitude:.5}");
+                    let res = ui.add(egui::Label::new(egui::RichText::new(text).color(ui.visuals().hyperlink_color)).truncate(true))
+                        .interact(egui::Sense::click())
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text("Show on OpenStreetMap");
+                    if res.clicked() {
+                        let url = format!("https://www.openstreetmap.org/?mlat={latitude}&mlon={longitude}#map=15/{latitude}/{longitude}");
+                        if let Err(error_value) = open::that_detached(url) {
+                            error = Some(ErrorWindow { 
+                                title: String::from("Map open"), 
+                                description: format!("Failed to open the map in the browser\n{error_value}")
+                            });
+                        }
+                    }
+                }
+                else {
+                    ui.label("--");
+                }
+                ui.end_row();
+                error
+            }).inner
+    }
+
     fn image_info_name(&mut self, ui: &mut egui::Ui) {
         ui.label("File name");
         let mut name = self.image_info_panel.as_ref().and_then(|panel| panel.rename.clone())
@@ -716,7 +1702,7 @@ impl EnchantedView {
                     let filename = format!("{}.{}", panel.target_name, panel.format_ext);
                     let mut path = panel.target_path.clone();
                     path.extend([filename].iter());
-                    if let Err(error) = image.image.save_with_format(path, panel.selected_format) {
+                    if let Err(error) = save_image(&image.image, &path, panel.selected_format) {
                         self.error = Some(ErrorWindow { 
                             title: String::from("Image conversion failed"), 
                             description: error.to_string() 
@@ -731,9 +1717,79 @@ impl EnchantedView {
                     eprintln!("Couldn't convert image, image is err");
                 }
             }    
+
+            let frame_count = self.image.as_ref().map(|image| image.frames.len()).unwrap_or(0);
+            if frame_count > 0 {
+                self.image_info_frame_export(ui, frame_count);
+            }
         });    
     }
 
+    fn image_info_frame_export(&mut self, ui: &mut egui::Ui, frame_count: usize) {
+        ui.add_space(ui.spacing().item_spacing.y * 2.0);
+        ui.label("Frame Export");
+        let Some(panel) = self.image_info_panel.as_mut() else { return };
+        let export = &mut panel.frame_export;
+        if export.frame_count != frame_count {
+            export.frame_count = frame_count;
+            export.range_start = 1;
+            export.range_end = frame_count;
+            export.exported = None;
+        }
+        let selections = vec![
+            RadioValue::new("Current frame", FrameSelection::Current),
+            RadioValue::new("Frame range", FrameSelection::Range),
+            RadioValue::new("All frames", FrameSelection::All)
+        ];
+        select(ui, "frame_export_selection", &mut export.selection, selections);
+        if export.selection == FrameSelection::Range {
+            ui.horizontal(|ui| {
+                ui.label("From");
+                ui.add(egui::DragValue::new(&mut export.range_start).clamp_range(1..=frame_count));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut export.range_end).clamp_range(export.range_start..=frame_count));
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut export.name_pattern)
+                .on_hover_text("{stem} is the name above, {frame} is the frame number, {frame:04} pads it with zeros and {ext} is the format extension.");
+        });
+        if ui.button("Export frames").clicked() {
+            if let (Ok(image), Some(panel)) = (self.image.as_ref(), self.image_info_panel.as_ref()) {
+                let export = &panel.frame_export;
+                let current = image.display.animation.as_ref().map(|animation| animation.current_frame()).unwrap_or(0);
+                let range = match export.selection {
+                    FrameSelection::Current => current..=current,
+                    FrameSelection::Range => {
+                        // the range might be from a previous image with more frames
+                        let end = export.range_end.min(frame_count);
+                        (export.range_start.min(end) - 1)..=(end - 1)
+                    },
+                    FrameSelection::All => 0..=(frame_count - 1),
+                };
+                let result = export_frames(&image.frames, range, &panel.target_path, &export.name_pattern, &panel.target_name, &panel.format_ext, panel.selected_format);
+                match result {
+                    Ok(count) => {
+                        if let Some(panel) = self.image_info_panel.as_mut() {
+                            panel.frame_export.exported = Some(count);
+                        }
+                    },
+                    Err(error) => {
+                        self.error = Some(ErrorWindow { 
+                            title: String::from("Frame export failed"), 
+                            description: error
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(count) = self.image_info_panel.as_ref().and_then(|panel| panel.frame_export.exported) {
+            let frames = if count == 1 { "frame" } else { "frames" };
+            ui.label(egui::RichText::new(format!("Exported {count} {frames}.")).color(ui.visuals().weak_text_color()));
+        }
+    }
+
     fn image_info_format_selection(&mut self, ui: &mut egui::Ui) {
         let selected_text = self.image_info_panel.as_ref()
             .and_then(|info| Some(format!("{:?}", info.selected_format).to_uppercase()))
@@ -810,15 +1866,180 @@ impl EnchantedView {
                 if res.clicked() {
                     self.next_image();
                 }
+                self.browse_menu(ui);
                 if let Some(directory) = &self.image_directory {
                     ui.centered_and_justified(|ui| {
-                        ui.label(format!("{} ({}/{})", directory.image_name(), directory.image_index() + 1, directory.count()));
+                        // the position is among the images that match the filter
+                        let position = directory.filtered_index().map_or_else(|| String::from("-"), |index| (index + 1).to_string());
+                        ui.label(format!("{} ({position}/{})", directory.image_label(), directory.count()));
                     });
                 }   
             });
         });
     }
 
+    /// The sort order and whether the subfolders are browsed too.
+    fn browse_menu(&mut self, ui: &mut egui::Ui) {
+        let order = self.settings.sort_order;
+        let arrow = if order.descending { "⏷" } else { "⏶" };
+        let mut new_order = order;
+        let mut toggle_subfolders = false;
+        let mut start_slideshow = false;
+        DropDownMenu::new(format!("{} {arrow}", order.key.label()), "sort_order")
+            .width(110.0)
+            .menu_width(130.0)
+            .ui(ui, |ui| {
+                for key in SortKey::ALL {
+                    if ui.add(Button::new(key.label()).selected(order.key == key)).clicked() {
+                        new_order.key = key;
+                    }
+                }
+                ui.separator();
+                if ui.add(Button::new("Ascending").selected(!order.descending)).clicked() {
+                    new_order.descending = false;
+                }
+                if ui.add(Button::new("Descending").selected(order.descending)).clicked() {
+                    new_order.descending = true;
+                }
+                ui.separator();
+                toggle_subfolders = ui.add(Button::new("Include subfolders").selected(self.settings.include_subfolders)).clicked();
+                if ui.add(Button::new("Show filmstrip").selected(self.settings.show_filmstrip)).clicked() {
+                    self.settings.show_filmstrip = !self.settings.show_filmstrip;
+                    self.settings.store(ui.ctx());
+                }
+                ui.separator();
+                let shortcut = ui.ctx().format_shortcut(&self.settings.key_binds.toggle_slideshow);
+                start_slideshow = ui.add_enabled(self.image_directory.is_some(), Button::new("Start slideshow").shortcut_text(shortcut)).clicked();
+            }).response.on_hover_text("Sort order and browsing");
+        if start_slideshow {
+            self.toggle_slideshow();
+        }
+        if new_order != order {
+            self.settings.sort_order = new_order;
+            self.settings.store(ui.ctx());
+            if let Some(directory) = self.image_directory.as_mut() {
+                directory.set_sort_order(new_order);
+            }
+            // the neighbours are different in the new order
+            self.preload_neighbours();
+        }
+        if toggle_subfolders {
+            let include_subfolders = !self.settings.include_subfolders;
+            let Some(mut directory) = self.image_directory.take() else {
+                // the next browsed directory uses it
+                self.settings.include_subfolders = include_subfolders;
+                self.settings.store(ui.ctx());
+                return;
+            };
+            let result = directory.set_include_subfolders(include_subfolders);
+            // the setting stays as the directory is browsed when it failed
+            if result.is_ok() {
+                self.settings.include_subfolders = include_subfolders;
+                self.settings.store(ui.ctx());
+            }
+            match result {
+                Ok(true) => self.load_image(directory.current_image_path()),
+                Ok(false) => (),
+                Err(error) => {
+                    self.error = Some(ErrorWindow { 
+                        title: String::from("Browsing Failed"), 
+                        description: error
+                    });
+                }
+            }
+            self.image_directory = Some(directory);
+            self.preload_neighbours();
+        }
+    }
+
+    /// Thumbnails of the images that match the filter, only the visible ones are loaded.
+    fn filmstrip(&mut self, ui: &mut egui::Ui) {
+        let Some(directory) = self.image_directory.as_ref() else { return };
+        let images: Vec<usize> = directory.filtered_images().map(|(index, _)| index).collect();
+        let current = directory.image_index();
+        let tile_size = egui::Vec2::splat(FILMSTRIP_HEIGHT);
+        let step = FILMSTRIP_HEIGHT + 4.0;
+        // only scrolled when the image changes so the strip can be scrolled freely
+        let scroll_to_current = self.filmstrip_index != Some(current);
+        self.filmstrip_index = Some(current);
+        let mut clicked = None;
+        ui.add_space(4.0);
+        egui::ScrollArea::horizontal().id_source("filmstrip").auto_shrink([false, true]).show_viewport(ui, |ui, viewport| {
+            ui.set_height(FILMSTRIP_HEIGHT);
+            ui.set_width(images.len() as f32 * step);
+            let origin = ui.min_rect().min;
+            let first = (viewport.min.x / step).floor().max(0.0) as usize;
+            let last = ((viewport.max.x / step).ceil() as usize).min(images.len());
+            for (position, index) in images.iter().enumerate() {
+                let rect = egui::Rect::from_min_size(origin + egui::vec2(position as f32 * step, 0.0), tile_size);
+                if *index == current && scroll_to_current {
+                    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                }
+                if !(first..last).contains(&position) {
+                    continue;
+                }
+                let path = directory.path(*index);
+                let res = ui.interact(rect, ui.id().with(path), egui::Sense::click());
+                let visuals = ui.style().interact(&res);
+                ui.painter().rect_filled(rect, visuals.rounding, visuals.bg_fill);
+                self.thumbnails.paint(ui.painter(), path, rect.shrink(2.0));
+                if *index == current {
+                    ui.painter().rect_stroke(rect, visuals.rounding, egui::Stroke::new(2.0, ui.visuals().selection.stroke.color));
+                }
+                let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                if res.on_hover_text(name).clicked() {
+                    clicked = Some(*index);
+                }
+            }
+        });
+        ui.add_space(4.0);
+        if let Some(index) = clicked.filter(|index| *index != current) {
+            let Some(mut directory) = self.image_directory.take() else { return };
+            let path = directory.go_to(index);
+            self.load_image(path);
+            self.image_directory = Some(directory);
+        }
+    }
+
+    fn animation_bar(&mut self, ui: &mut egui::Ui) {
+        let Ok(image) = self.image.as_mut() else { return };
+        let Some(animation) = image.display.animation.as_mut() else { return };
+        let key_binds = &self.settings.key_binds;
+        ui.add_space(5.0);
+        ui.allocate_ui_with_layout(egui::vec2(ui.available_width(), 24.0), egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            CenterContainer::new(egui::vec2(ui.available_width(), 24.0)).inner_layout(egui::Layout::left_to_right(egui::Align::Center)).ui(ui, |ui| {
+                let previous = ui.button("⏮").on_hover_text(format!("Previous frame ({})", ui.ctx().format_shortcut(&key_binds.previous_frame)));
+                if previous.clicked() {
+                    animation.playing = false;
+                    animation.previous_frame();
+                }
+                let (icon, tooltip) = if animation.playing { ("⏸", "Pause") } else { ("⏵", "Play") };
+                let playback = ui.button(icon).on_hover_text(format!("{tooltip} ({})", ui.ctx().format_shortcut(&key_binds.toggle_playback)));
+                if playback.clicked() {
+                    animation.toggle_playback();
+                }
+                let next = ui.button("⏭").on_hover_text(format!("Next frame ({})", ui.ctx().format_shortcut(&key_binds.next_frame)));
+                if next.clicked() {
+                    animation.playing = false;
+                    animation.next_frame();
+                }
+                ui.label(format!("Frame {}/{}", animation.current_frame() + 1, animation.frame_count()));
+                ui.separator();
+                ui.toggle_value(&mut animation.looping, "🔁 Loop");
+                DropDownMenu::new(format!("{}x", animation.speed), "playback_speed")
+                    .width(60.0)
+                    .menu_width(60.0)
+                    .ui(ui, |ui| {
+                        for speed in PLAYBACK_SPEEDS {
+                            if ui.add(Button::new(format!("{speed}x")).selected(animation.speed == speed)).clicked() {
+                                animation.speed = speed;
+                            }
+                        }
+                    });
+            });
+        });
+    }
+
     fn handle_drop_files(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
         let hovered_file = ui.input(|input| {
             input.raw.hovered_files
@@ -845,9 +2066,10 @@ impl EnchantedView {
         });
         if let Some(dropped_file) = dropped_file {
             let path = dropped_file.path.expect("The path must exist.");
-            self.image_directory = Some(ImageDirectory::new(&path).expect("Unable to initialize the image directory."));
+            self.opened_path = Some(path.clone());
+            self.browse_directory_of(&path);
             if let Some(bytes) = dropped_file.bytes {
-                self.load_image_raw(&*bytes, &path);
+                self.load_image_raw(bytes, &path);
             }
             else {
                 self.load_image(&path);
@@ -864,15 +2086,107 @@ impl EnchantedView {
             let back_button = ImageButton::new(egui::include_image!("../assets/back_arrow.png"))
                 .tint(self.theme.image_button().color);
             if back_button.ui(ui).clicked() {
-                self.settings_screen = false;
+                self.screen = Screen::Main;
             }
             ui.heading("Settings");
         });
         if let Some(update) = self.settings.show(ui, &self.theme) {
             match update {
-                settings::SettingsUpdate::ReloadTheme => self.update_theme(),
-                settings::SettingsUpdate::ReloadTexture => self.reload_texture(),
+                settings::SettingsUpdate::ReloadTheme => {
+                    // picking a theme in the settings replaces the one from the command line
+                    self.overrides.theme = None;
+                    self.update_theme();
+                },
+                settings::SettingsUpdate::ReloadTexture => {
+                    self.overrides.image_filtering = None;
+                    self.reload_texture();
+                },
                 settings::SettingsUpdate::CloseColorAnalyzer => self.color_analyzer.open = None,
+                settings::SettingsUpdate::ResizeCache => self.image_cache.set_budget(self.settings.cache_budget_bytes()),
+            }
+        }
+    }
+
+    /// Thumbnails of every image in the directory that matches the filter, only the visible rows are laid out.
+    fn gallery_screen(&mut self, ui: &mut egui::Ui) {
+        let toolbar_size = egui::vec2(ui.available_width(), 30.0);
+        ui.allocate_ui_with_layout(toolbar_size, egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            self.theme.style_image_button(ui);
+            ui.add_space(5.0);
+            let back_button = ImageButton::new(egui::include_image!("../assets/back_arrow.png"))
+                .tint(self.theme.image_button().color);
+            if back_button.ui(ui).clicked() {
+                self.screen = Screen::Main;
+            }
+            ui.heading("Gallery");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.add_space(5.0);
+                let res = ui.add(egui::Slider::new(&mut self.settings.gallery_tile_size, 64.0..=256.0).show_value(false))
+                    .on_hover_text("Thumbnail size");
+                if res.changed() {
+                    self.settings.store(ui.ctx());
+                }
+            });
+        });
+        ui.separator();
+        let Some(directory) = self.image_directory.as_ref() else {
+            self.screen = Screen::Main;
+            return;
+        };
+        let images: Vec<usize> = directory.filtered_images().map(|(index, _)| index).collect();
+        let current = directory.image_index();
+        let tile_size = self.settings.gallery_tile_size;
+        let thumbnails = if tile_size <= ThumbnailSize::Normal.pixels() as f32 {
+            &mut self.thumbnails
+        }
+        else {
+            self.large_thumbnails.get_or_insert_with(|| Thumbnails::new(ui.ctx(), ThumbnailSize::Large))
+        };
+        let spacing = 8.0;
+        let name_height = ui.text_style_height(&egui::TextStyle::Small);
+        let row_height = tile_size + name_height + 4.0;
+        let columns = ((ui.available_width() - spacing) / (tile_size + spacing)).floor().max(1.0) as usize;
+        let rows = images.len().div_ceil(columns);
+        let mut scroll_area = egui::ScrollArea::vertical().id_source("gallery").auto_shrink([false, false]);
+        if self.gallery_scroll_to_current {
+            self.gallery_scroll_to_current = false;
+            let row = images.iter().position(|index| *index == current).unwrap_or(0) / columns;
+            scroll_area = scroll_area.vertical_scroll_offset(row as f32 * (row_height + spacing));
+        }
+        let mut opened = None;
+        ui.spacing_mut().item_spacing = egui::vec2(spacing, spacing);
+        scroll_area.show_rows(ui, row_height, rows, |ui, row_range| {
+            for row in row_range {
+                ui.horizontal(|ui| {
+                    ui.add_space(spacing);
+                    let end = ((row + 1) * columns).min(images.len());
+                    for index in &images[row * columns..end] {
+                        let path = directory.path(*index);
+                        let (rect, res) = ui.allocate_exact_size(egui::vec2(tile_size, row_height), egui::Sense::click());
+                        let visuals = ui.style().interact(&res);
+                        ui.painter().rect_filled(rect, visuals.rounding, visuals.bg_fill);
+                        let image_rect = egui::Rect::from_min_size(rect.min, egui::Vec2::splat(tile_size));
+                        thumbnails.paint(ui.painter(), path, image_rect.shrink(4.0));
+                        if *index == current {
+                            ui.painter().rect_stroke(rect, visuals.rounding, egui::Stroke::new(2.0, ui.visuals().selection.stroke.color));
+                        }
+                        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                        let name_rect = egui::Rect::from_min_max(egui::pos2(rect.min.x + 4.0, image_rect.max.y), egui::pos2(rect.max.x - 4.0, rect.max.y));
+                        ui.painter().with_clip_rect(name_rect).text(name_rect.center_top(), egui::Align2::CENTER_TOP, &name, egui::TextStyle::Small.resolve(ui.style()), visuals.text_color());
+                        if res.on_hover_text(name).double_clicked() {
+                            opened = Some(*index);
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(index) = opened {
+            self.screen = Screen::Main;
+            if index != current {
+                let Some(mut directory) = self.image_directory.take() else { return };
+                let path = directory.go_to(index);
+                self.load_image(path);
+                self.image_directory = Some(directory);
             }
         }
     }
@@ -908,22 +2222,131 @@ impl EnchantedView {
 impl eframe::App for EnchantedView {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.check_for_image_updates();
+        #[cfg(unix)]
+        self.handle_control_requests(frame);
+        // the image sets it again while it's shown
+        let hovered = std::mem::take(&mut self.image_hovered);
+        if self.slideshow.as_mut().is_some_and(|slideshow| slideshow.update(ctx, hovered)) {
+            self.advance_slideshow();
+        }
         egui::CentralPanel::default().frame(egui::Frame::central_panel(&*ctx.style()).inner_margin(0.0)).show(ctx, |ui| {
             ui.set_enabled(self.error.is_none());
             
-            if self.settings_screen {
-                self.settings_screen(ui);
-            }
-            else {
-                self.main_screen(ui, frame);
+            match self.screen {
+                Screen::Main => self.main_screen(ui, frame),
+                Screen::Settings => self.settings_screen(ui),
+                Screen::Gallery => self.gallery_screen(ui)
             }
         });
 
         self.error_popup(ctx);
+        if self.settings.restore_session {
+            self.store_session(ctx);
+        }
+    }
+}
+
+/// Starts loading the images from the command line.
+/// A single image is browsed with the other images in its directory, like an image opened from the file dialog.
+fn open_paths(paths: &[PathBuf], index: Option<usize>, settings: &Settings, context: &egui::Context, tone_mapping: &ToneMapping) -> Result<(Option<LoadHandle>, Option<ImageDirectory>), String> {
+    // the index on the command line starts from 1
+    let index = index.map(|index| index.saturating_sub(1));
+    let (sort_order, include_subfolders) = (settings.sort_order, settings.include_subfolders);
+    match paths {
+        [] => Ok((None, None)),
+        [image_path] if !image_path.is_dir() => {
+            // io errors are reported once the loader fails to read the file
+            let mut directory = if image_path.is_file() && fs::File::open(image_path).is_ok() {
+                let path = image_path.canonicalize().expect("Couldn't find absolute path");
+                Some(ImageDirectory::new(&path, sort_order, include_subfolders).map_err(|error| error.to_string())?)
+            } else { None };
+            let path = match (directory.as_mut(), index) {
+                (Some(directory), Some(index)) => directory.go_to(index).clone(),
+                _ => image_path.clone()
+            };
+            Ok((Some(ImageLoader::file(&path).load(context, tone_mapping)), directory))
+        },
+        paths => {
+            let paths: Vec<PathBuf> = paths.iter().filter_map(|path| path.canonicalize().ok()).collect();
+            let mut directory = ImageDirectory::from_paths(&paths, sort_order, include_subfolders)
+                .map_err(|error| error.to_string())?
+                .ok_or_else(|| String::from("No images were found in the given paths."))?;
+            let path = directory.go_to(index.unwrap_or(0)).clone();
+            Ok((Some(ImageLoader::file(&path).load(context, tone_mapping)), Some(directory)))
+        }
     }
 }
 
-fn load_metadata(path: &PathBuf, image_load_result: &ImageLoadResult) -> ImageMetadata {
+// the path of the image that is loading after opening some paths
+fn loading_path(opened: &Result<(Option<LoadHandle>, Option<ImageDirectory>), String>) -> Option<PathBuf> {
+    opened.as_ref().ok()?.0.as_ref().map(|handle| handle.path().clone())
+}
+
+/// The recent images as thumbnail tiles, returns the one that was clicked.
+fn recent_files_grid(ui: &mut egui::Ui, paths: &[PathBuf], thumbnails: &mut Thumbnails, theme: &Theme) -> Option<PathBuf> {
+    if paths.is_empty() {
+        return None;
+    }
+    ui.add_space(10.0);
+    ui.label(egui::RichText::new("Recent images").text_style(theme.heading3()));
+    let mut clicked = None;
+    let spacing = ui.spacing().item_spacing.x;
+    let columns = ((ui.available_width() / (RECENT_THUMBNAIL_SIZE + spacing)) as usize).clamp(1, paths.len());
+    for row in paths.chunks(columns) {
+        // the row is only as wide as its tiles so it's centered like the rest of the screen
+        let row_size = egui::vec2(row.len() as f32 * (RECENT_THUMBNAIL_SIZE + spacing) - spacing, RECENT_THUMBNAIL_SIZE);
+        ui.allocate_ui_with_layout(row_size, egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            for path in row {
+                let (rect, res) = ui.allocate_exact_size(egui::Vec2::splat(RECENT_THUMBNAIL_SIZE), egui::Sense::click());
+                let visuals = ui.style().interact(&res);
+                ui.painter().rect(rect, visuals.rounding, visuals.bg_fill, visuals.bg_stroke);
+                thumbnails.paint(ui.painter(), path, rect.shrink(4.0));
+                let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                if res.on_hover_text(format!("{name}\n{}", format_path(path))).clicked() {
+                    clicked = Some(path.clone());
+                }
+            }
+        });
+    }
+    clicked
+}
+
+/// Opens the image of the last session, or the image at the same place in its directory when it's gone.
+fn restore_session(session: &LastSession, settings: &Settings, context: &egui::Context) -> Result<(Option<LoadHandle>, Option<ImageDirectory>), String> {
+    // the tone mapping isn't part of the session
+    let tone_mapping = ToneMapping::default();
+    if session.path.is_file() {
+        return open_paths(std::slice::from_ref(&session.path), None, settings, context, &tone_mapping);
+    }
+    match session.path.parent() {
+        // a directory without images opens the empty screen instead of an error
+        Some(directory) if directory.is_dir() => open_paths(&[directory.to_path_buf()], Some(session.index + 1), settings, context, &tone_mapping).or(Ok((None, None))),
+        _ => Ok((None, None))
+    }
+}
+
+/// Shows the element and its children, `highlighted` is set to the bounds of the hovered element.
+fn svg_element_tree(ui: &mut egui::Ui, element: &SvgElement, id: egui::Id, depth: usize, highlighted: &mut Option<egui::Rect>) {
+    let res = if element.children.is_empty() {
+        ui.label(&element.label)
+    }
+    else {
+        egui::CollapsingHeader::new(&element.label)
+            .id_source(id)
+            .default_open(depth == 0)
+            .show(ui, |ui| {
+                for (index, child) in element.children.iter().enumerate() {
+                    svg_element_tree(ui, child, id.with(index), depth + 1, highlighted);
+                }
+            })
+            .header_response
+    };
+    if res.hovered() {
+        *highlighted = element.bounds;
+    }
+}
+
+fn load_metadata(path: &Path, image_load_result: &ImageLoadResult) -> ImageMetadata {
     if let Ok(metadata) = fs::metadata(path) {
         ImageMetadata {
             file_size: metadata.len() as u128,
@@ -932,28 +2355,42 @@ fn load_metadata(path: &PathBuf, image_load_result: &ImageLoadResult) -> ImageMe
             date_accessed: metadata.accessed().ok().and_then(|date| Some(date.into())),
             read_only: Some(metadata.permissions().readonly()),
             file_format: image_load_result.file_format,
-            inner_format: image_load_result.inner_format
+            inner_format: image_load_result.inner_format,
+            photo_metadata: image_load_result.photo_metadata.clone()
         }
     }
     else {
-        Default::default()
+        // pasted images have no file but the format is still known
+        ImageMetadata {
+            file_format: image_load_result.file_format,
+            inner_format: image_load_result.inner_format,
+            photo_metadata: image_load_result.photo_metadata.clone(),
+            ..Default::default()
+        }
     }
 }
 
-fn image_or_error(res: ImageResult<ImageLoadResult>, path: &PathBuf, theme: &Theme) -> Result<OpenedImage, Option<String>> {
+fn image_or_error(res: ImageResult<ImageLoadResult>, path: &Path, theme: &Theme) -> Result<OpenedImage, Option<String>> {
     match res {
         Ok(image_load_result) => {
             let image_size = egui::vec2(image_load_result.image.width() as f32, image_load_result.image.height() as f32);
             let metadata = load_metadata(path, &image_load_result);
+            let delays = image_load_result.frames.iter().map(|frame| frame.delay).collect();
             Ok(OpenedImage {
                 image: image_load_result.image, 
+                frames: image_load_result.frames,
+                pages: image_load_result.pages,
+                svg: image_load_result.svg.map(SvgView::new),
+                color_profile: image_load_result.color_profile,
+                tiles: image_load_result.tiles,
+                pending_display: None,
                 display: PanZoomImage::new(
                     true, 
                     true, 
                     image_load_result.handle, 
                     image_size, 
                     theme.checkerboard_pattern_colors()
-                ),
+                ).with_animation(image_load_result.frame_textures, delays),
                 metadata
             })
         },
@@ -0,0 +1,180 @@
+use std::{cmp::Reverse, io::Cursor, sync::Arc};
+
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use tiff::{ColorType, decoder::{Decoder, DecodingResult}};
+
+// the size of the ico header and one directory entry, the image data of a single entry ico comes after them
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+
+/// The pages of a multi page file.
+/// The shown page is moved out to the opened image so it isn't stored twice, its slot holds an empty image.
+#[derive(Clone)]
+pub struct Pages {
+    pages: Vec<Arc<DynamicImage>>,
+    sizes: Vec<(u32, u32)>,
+    current: usize
+}
+
+impl Pages {
+    /// Returns the first page and the pages, `None` if there is only one page.
+    pub fn split_first(pages: Vec<DynamicImage>) -> Option<(Arc<DynamicImage>, Self)> {
+        if pages.len() < 2 {
+            return None;
+        }
+        let sizes = pages.iter().map(|page| (page.width(), page.height())).collect();
+        let mut pages: Vec<Arc<DynamicImage>> = pages.into_iter().map(Arc::new).collect();
+        let first = std::mem::take(&mut pages[0]);
+        Some((first, Self { pages, sizes, current: 0 }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn sizes(&self) -> &[(u32, u32)] {
+        &self.sizes
+    }
+
+    /// Puts the shown image back in its slot and moves `page` out to `image`.
+    pub fn switch(&mut self, image: &mut Arc<DynamicImage>, page: usize) {
+        if page == self.current || page >= self.pages.len() {
+            return;
+        }
+        std::mem::swap(image, &mut self.pages[self.current]);
+        std::mem::swap(image, &mut self.pages[page]);
+        self.current = page;
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.pages.iter().map(|page| page.as_bytes().len()).sum()
+    }
+}
+
+/// Returns all the pages of a multi page file, an empty vec is returned for files with a single image.
+/// Pages that can't be decoded are skipped so one broken page doesn't hide the rest of the file.
+pub fn decode_pages(bytes: &[u8], format: ImageFormat) -> Vec<DynamicImage> {
+    let pages = match format {
+        ImageFormat::Tiff => decode_tiff_pages(bytes),
+        ImageFormat::Ico => decode_ico_pages(bytes),
+        _ => Vec::new()
+    };
+    if pages.len() < 2 {
+        return Vec::new();
+    }
+    pages
+}
+
+fn decode_tiff_pages(bytes: &[u8]) -> Vec<DynamicImage> {
+    let mut pages = Vec::new();
+    let Ok(mut decoder) = Decoder::new(Cursor::new(bytes)) else {
+        return pages;
+    };
+    loop {
+        if let Some(page) = decode_tiff_page(&mut decoder) {
+            pages.push(page);
+        }
+        if !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+    pages
+}
+
+fn decode_tiff_page(decoder: &mut Decoder<Cursor<&[u8]>>) -> Option<DynamicImage> {
+    let (width, height) = decoder.dimensions().ok()?;
+    let color_type = decoder.colortype().ok()?;
+    match (decoder.read_image().ok()?, color_type) {
+        (DecodingResult::U8(data), ColorType::Gray(1)) => {
+            // scanned documents are often black and white, every row starts at a new byte
+            let row_size = (width as usize).div_ceil(8);
+            let pixels = data.chunks(row_size)
+                .take(height as usize)
+                .flat_map(|row| (0..width as usize).map(move |x| if row[x / 8] & (0x80 >> (x % 8)) != 0 { 255 } else { 0 }))
+                .collect();
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        },
+        (DecodingResult::U8(data), ColorType::Gray(8)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+        (DecodingResult::U8(data), ColorType::GrayA(8)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8),
+        (DecodingResult::U8(data), ColorType::RGB(8)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+        (DecodingResult::U8(data), ColorType::RGBA(8)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8),
+        (DecodingResult::U16(data), ColorType::Gray(16)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16),
+        (DecodingResult::U16(data), ColorType::GrayA(16)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16),
+        (DecodingResult::U16(data), ColorType::RGB(16)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16),
+        (DecodingResult::U16(data), ColorType::RGBA(16)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16),
+        (DecodingResult::F32(data), ColorType::RGB(32)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb32F),
+        (DecodingResult::F32(data), ColorType::RGBA(32)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba32F),
+        _ => None
+    }
+}
+
+/// Every size in the file is decoded by giving the image crate an ico with only that entry, the biggest comes first.
+fn decode_ico_pages(bytes: &[u8]) -> Vec<DynamicImage> {
+    let count = bytes.get(4..6).map_or(0, |count| u16::from_le_bytes([count[0], count[1]]) as usize);
+    let mut pages: Vec<DynamicImage> = (0..count)
+        .filter_map(|index| {
+            let entry_start = ICO_HEADER_SIZE + index * ICO_ENTRY_SIZE;
+            let entry = bytes.get(entry_start..entry_start + ICO_ENTRY_SIZE)?;
+            let size = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+            let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+            let data = bytes.get(offset..offset.checked_add(size)?)?;
+            let mut single = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE + size);
+            single.extend_from_slice(&bytes[0..4]);
+            single.extend_from_slice(&1u16.to_le_bytes());
+            single.extend_from_slice(&entry[..8]);
+            single.extend_from_slice(&(size as u32).to_le_bytes());
+            single.extend_from_slice(&((ICO_HEADER_SIZE + ICO_ENTRY_SIZE) as u32).to_le_bytes());
+            single.extend_from_slice(data);
+            image::load_from_memory_with_format(&single, ImageFormat::Ico).ok()
+        })
+        .collect();
+    pages.sort_by_key(|page| Reverse(page.width() * page.height()));
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_ico_sizes() {
+        let sizes = [16, 48, 32];
+        let images: Vec<Vec<u8>> = sizes.iter()
+            .map(|size| {
+                let mut png = Vec::new();
+                DynamicImage::new_rgba8(*size, *size).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).expect("Encoding to memory can't fail.");
+                png
+            })
+            .collect();
+        let mut ico = vec![0, 0, 1, 0, sizes.len() as u8, 0];
+        let mut offset = ICO_HEADER_SIZE + ICO_ENTRY_SIZE * sizes.len();
+        for (size, png) in sizes.iter().zip(&images) {
+            ico.extend_from_slice(&[*size as u8, *size as u8, 0, 0, 1, 0, 32, 0]);
+            ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += png.len();
+        }
+        ico.extend(images.concat());
+        let pages = decode_pages(&ico, ImageFormat::Ico);
+        let widths: Vec<u32> = pages.iter().map(|page| page.width()).collect();
+        assert_eq!(widths, [48, 32, 16]);
+    }
+
+    #[test]
+    fn switches_pages() {
+        let pages = (1..=3).map(|size| DynamicImage::new_luma8(size, size)).collect();
+        let (mut image, mut pages) = Pages::split_first(pages).expect("There are multiple pages.");
+        assert_eq!(image.width(), 1);
+        pages.switch(&mut image, 2);
+        assert_eq!(image.width(), 3);
+        pages.switch(&mut image, 0);
+        assert_eq!(image.width(), 1);
+        pages.switch(&mut image, 1);
+        assert_eq!((image.width(), pages.current()), (2, 1));
+        assert!(Pages::split_first(vec![DynamicImage::new_luma8(1, 1)]).is_none());
+    }
+}
@@ -1,10 +1,17 @@
+use std::time::Duration;
+
 use egui::{Sense, Color32};
-use crate::{egui_extensions::{ContextEx, PainterEx, Vec2Ex}, checkerboard_pattern::generate_checkerboard_pattern};
+use crate::{egui_extensions::{ContextEx, PainterEx, Vec2Ex}, checkerboard_pattern::generate_checkerboard_pattern, animation::{AnimationPlayer, FrameTextures}, tiled_texture::TiledTexture};
+
+const UV_CENTER: egui::Vec2 = egui::vec2(0.5, 0.5);
 
 pub struct PanZoomImage {
     pub constrain_to_image: bool,
     pub always_center: bool,
-    pub texture_handle: egui::TextureHandle,
+    pub texture_handle: TiledTexture,
+    // the frames of an animated image
+    frame_textures: Option<FrameTextures>,
+    pub animation: Option<AnimationPlayer>,
     // The actual texture dimensions
     texture_size: egui::Vec2,
     // image size will switch the axises on rotation
@@ -17,15 +24,23 @@ pub struct PanZoomImage {
     min_scale: f32,
     pub max_scale: f32,
     fit_request: bool,
-    checkerboard_colors: [Color32; 2]
+    // applied once after the image is fitted, the offset is kept when there is none
+    view_request: Option<(f32, Option<egui::Vec2>)>,
+    checkerboard_colors: [Color32; 2],
+    // below 1 while the image fades in
+    pub opacity: f32,
+    // off while a region of the image is selected by dragging
+    pub drag_to_pan: bool
 }
 
 impl PanZoomImage {
-    pub fn new(constrain_to_image: bool, always_center: bool, texture_handle: egui::TextureHandle, texture_size: egui::Vec2, checkerboard_colors: [Color32; 2]) -> Self {
+    pub fn new(constrain_to_image: bool, always_center: bool, texture_handle: TiledTexture, texture_size: egui::Vec2, checkerboard_colors: [Color32; 2]) -> Self {
         Self {
             constrain_to_image,
             always_center,
             texture_handle,
+            frame_textures: None,
+            animation: None,
             offset: egui::vec2(0.0, 0.0),
             scale: 1.0,
             checkers_mesh: egui::Shape::Noop,
@@ -33,11 +48,57 @@ impl PanZoomImage {
             last_image_rect: egui::Rect { min: egui::pos2(0.0, 0.0), max: egui::pos2(0.0, 0.0) },
             min_scale: 0.0,
             max_scale: 32.0,
-            texture_size: texture_size,
+            texture_size,
             image_size: texture_size,
             fit_request: true,
-            checkerboard_colors: checkerboard_colors
+            view_request: None,
+            checkerboard_colors,
+            opacity: 1.0,
+            drag_to_pan: true
+        }
+    }
+
+    pub fn with_animation(mut self, frame_textures: FrameTextures, delays: Vec<Duration>) -> Self {
+        if !frame_textures.is_empty() {
+            self.frame_textures = Some(frame_textures);
+            self.animation = Some(AnimationPlayer::new(delays));
         }
+        self
+    }
+
+    /// Shows a different image, like another page of the file.
+    pub fn set_texture(&mut self, texture_handle: TiledTexture, texture_size: egui::Vec2) {
+        self.texture_handle = texture_handle;
+        self.texture_size = texture_size;
+        self.fit_request = true;
+    }
+
+    pub fn set_frame_textures(&mut self, frame_textures: FrameTextures) {
+        self.frame_textures = Some(frame_textures).filter(|frame_textures| !frame_textures.is_empty());
+    }
+
+    fn current_frame(&self) -> Option<usize> {
+        self.animation.as_ref().map(|animation| animation.current_frame())
+    }
+
+    fn current_texture(&self) -> &TiledTexture {
+        self.current_frame()
+            .and_then(|frame| self.frame_textures.as_ref()?.get(frame))
+            .unwrap_or(&self.texture_handle)
+    }
+
+    /// Zooms to `scale` around the center of the image on the next update.
+    pub fn request_scale(&mut self, scale: f32) {
+        self.view_request = Some((scale, None));
+    }
+
+    /// Restores a scale and offset from `offset()` on the next update.
+    pub fn request_view(&mut self, scale: f32, offset: egui::Vec2) {
+        self.view_request = Some((scale, Some(offset)));
+    }
+
+    pub fn offset(&self) -> egui::Vec2 {
+        self.offset
     }
 
     pub fn zoom_to_original(&mut self) {
@@ -79,6 +140,41 @@ impl PanZoomImage {
         (pos.x as u32, pos.y as u32)
     }
 
+    /// Maps a rect in texture pixels to the screen, `flip_horizontal`, `flip_vertical` and `rotation` should match the last update.
+    pub fn image_rect_to_screen(&self, rect: egui::Rect, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> egui::Rect {
+        let min = self.uv_to_screen((rect.min.to_vec2() / self.texture_size).to_pos2(), flip_horizontal, flip_vertical, rotation);
+        let max = self.uv_to_screen((rect.max.to_vec2() / self.texture_size).to_pos2(), flip_horizontal, flip_vertical, rotation);
+        egui::Rect::from_two_pos(min, max)
+    }
+
+    /// Maps a point on the screen to texture pixels, the inverse of `image_rect_to_screen`.
+    pub fn screen_to_image(&self, screen: egui::Pos2, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> egui::Pos2 {
+        (self.screen_to_uv(screen, flip_horizontal, flip_vertical, rotation).to_vec2() * self.texture_size).to_pos2()
+    }
+
+    fn uv_to_screen(&self, uv: egui::Pos2, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> egui::Pos2 {
+        // rotating the other way around undoes the rotation of screen_to_uv
+        let mut point = uv.rotate90_around(UV_CENTER, 4 - rotation % 4);
+        if flip_horizontal {
+            point.x = 1.0 - point.x;
+        }
+        if flip_vertical {
+            point.y = 1.0 - point.y;
+        }
+        self.world_to_screen(self.last_rect.min.to_vec2() + point.to_vec2() * self.image_size).to_pos2()
+    }
+
+    fn screen_to_uv(&self, screen: egui::Pos2, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> egui::Pos2 {
+        let mut point = ((self.screen_to_world(screen.to_vec2()) - self.last_rect.min.to_vec2()) / self.image_size).to_pos2();
+        if flip_horizontal {
+            point.x = 1.0 - point.x;
+        }
+        if flip_vertical {
+            point.y = 1.0 - point.y;
+        }
+        point.rotate90_around(UV_CENTER, rotation)
+    }
+
     fn world_to_screen(&self, world: egui::Vec2) -> egui::Vec2 {
         egui::Vec2 {
             x: (world.x - self.offset.x) * self.scale, 
@@ -109,6 +205,13 @@ impl PanZoomImage {
         const DEBUG: bool = false;
         // TODO: animate the scaling to be smooth
 
+        if let Some(animation) = self.animation.as_mut() {
+            animation.update(ui.ctx().delta_time());
+            if let Some(wait) = animation.time_until_next_frame() {
+                ui.ctx().request_repaint_after(wait);
+            }
+        }
+
         let fit_scale = self.calc_fit_scale(self.last_rect);
         // Because of float rounding errors its not possible to just compare them with ==
         if (self.scale - fit_scale).abs() < 0.00001 {
@@ -140,8 +243,15 @@ impl PanZoomImage {
             self.zoom_to_fit();
             self.fit_request = false;
         }
+        if let Some((scale, offset)) = self.view_request.take() {
+            let center = (self.world_to_screen(rect.min.to_vec2()) + self.world_to_screen(rect.min.to_vec2() + self.image_size)) / 2.0;
+            self.set_zoom(scale, center);
+            if let Some(offset) = offset {
+                self.offset = offset;
+            }
+        }
         // panning
-        if res.dragged() {
+        if res.dragged() && self.drag_to_pan {
             // if there is a flip then the offset should be the other way around
             self.offset -=  res.drag_delta() / self.scale * egui::vec2(if flip_horizontal {-1.0} else {1.0}, if flip_vertical {-1.0} else {1.0});
         }
@@ -209,11 +319,20 @@ impl PanZoomImage {
         // TODO: find a way to remove the clone
         // A better way to do the checkers background is using texture tiling(in the shader) but this is not available in egui at the moment
         // Waiting for issue #3481 in egui
-        let mesh = self.checkers_mesh.clone();
+        let mut mesh = self.checkers_mesh.clone();
+        if let egui::Shape::Mesh(mesh) = &mut mesh {
+            if self.opacity < 1.0 {
+                mesh.vertices.iter_mut().for_each(|vertex| vertex.color = vertex.color.gamma_multiply(self.opacity));
+            }
+        }
         ui.painter().add(mesh);
 
-        let mesh = self.generate_image_mesh(image_min, image_max, image_rect, rect, flip_horizontal, flip_vertical, rotation);
-        ui.painter().add(egui::Shape::mesh(mesh));
+        if let (Some(frame), Some(frame_textures)) = (self.current_frame(), self.frame_textures.as_mut()) {
+            frame_textures.load(frame);
+        }
+        for mesh in self.generate_image_meshes(rect, flip_horizontal, flip_vertical, rotation) {
+            ui.painter().add(egui::Shape::mesh(mesh));
+        }
 
         let is_hovering = ui.ctx().rect_contains_pointer(ui.layer_id(), image_rect);
         if highlight_hovered_pixel && is_hovering {
@@ -233,85 +352,32 @@ impl PanZoomImage {
         res
     }
 
-    fn generate_image_mesh(&self, image_min: egui::Vec2, image_max: egui::Vec2, image_rect: egui::Rect, rect: egui::Rect, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> egui::Mesh {
+    /// Generates one mesh for every tile of the texture that is visible in `rect`.
+    fn generate_image_meshes(&self, rect: egui::Rect, flip_horizontal: bool, flip_vertical: bool, rotation: usize) -> Vec<egui::Mesh> {
         use egui::epaint::Vertex;
 
-        let mut clipped = self.clipping_uv(image_min, image_max, rect);
-        // apply flipping
-        let max = clipped.max;
-        if flip_horizontal {
-            clipped.max.x = clipped.min.x;
-            clipped.min.x = max.x;
-        }
-        if flip_vertical {
-            clipped.max.y = clipped.min.y;
-            clipped.min.y = max.y;
-        }
-
-        // rotating
-        let uv_center = egui::vec2(0.5, 0.5);
-        let left_top = clipped.left_top().rotate90_around(uv_center, rotation);
-        let right_top = clipped.right_top().rotate90_around(uv_center, rotation);
-        let right_bottom = clipped.right_bottom().rotate90_around(uv_center, rotation);
-        let left_bottom = clipped.left_bottom().rotate90_around(uv_center, rotation);
-
-        let mut mesh = egui::Mesh::with_texture(self.texture_handle.id());
-        let idx = mesh.vertices.len() as u32;
-        mesh.reserve_triangles(2);
-        mesh.reserve_vertices(4);
-
-        mesh.add_triangle(idx + 0, idx + 1, idx + 2);
-        mesh.add_triangle(idx + 2, idx + 3, idx + 0);
-
-        mesh.vertices.push(Vertex {
-            pos: image_rect.left_top(),
-            uv: left_top,
-            color: Color32::WHITE,
-        });
-        mesh.vertices.push(Vertex {
-            pos: image_rect.right_top(),
-            uv: right_top,
-            color: Color32::WHITE,
-        });
-        mesh.vertices.push(Vertex {
-            pos: image_rect.right_bottom(),
-            uv: right_bottom,
-            color: Color32::WHITE,
-        });
-        mesh.vertices.push(Vertex {
-            pos: image_rect.left_bottom(),
-            uv: left_bottom,
-            color: Color32::WHITE,
-        });
-
-        mesh
-    }
-
-    
-
-    fn clipping_uv(&self, image_min: egui::Vec2, image_max: egui::Vec2, rect: egui::Rect) -> egui::Rect {
-        let mut uv_min_x = 0.0;
-        if image_min.x < rect.min.x {
-            uv_min_x = ((rect.min.x - image_min.x) / self.scale) / self.image_size.x;
-        }
-        let mut uv_min_y = 0.0;
-        if image_min.y < rect.min.y {
-            uv_min_y = ((rect.min.y - image_min.y) / self.scale) / self.image_size.y;
-        }
-        let mut uv_max_x = 1.0;
-        if image_max.x > rect.max.x {
-            uv_max_x = (self.image_size.x + (rect.max.x - image_max.x) / self.scale) / self.image_size.x;
-        }
-        let mut uv_max_y = 1.0;
-        if image_max.y > rect.max.y {
-            uv_max_y = (self.image_size.y + (rect.max.y - image_max.y) / self.scale) / self.image_size.y;
-        }
-
+        self.current_texture().tiles().iter().filter_map(|tile| {
+            let tile_min = self.uv_to_screen(tile.uv.min, flip_horizontal, flip_vertical, rotation);
+            let tile_max = self.uv_to_screen(tile.uv.max, flip_horizontal, flip_vertical, rotation);
+            // only the part of the tile that is inside the rect is drawn
+            let visible = egui::Rect::from_two_pos(tile_min, tile_max).intersect(rect);
+            if !visible.is_positive() {
+                return None;
+            }
 
-        egui::Rect {
-            min: (egui::vec2(uv_min_x, uv_min_y)).to_pos2(),
-            max: (egui::vec2(uv_max_x, uv_max_y)).to_pos2(),
-        }
+            let mut mesh = egui::Mesh::with_texture(tile.handle.id());
+            mesh.reserve_triangles(2);
+            mesh.reserve_vertices(4);
+            mesh.add_triangle(0, 1, 2);
+            mesh.add_triangle(2, 3, 0);
+            for pos in [visible.left_top(), visible.right_top(), visible.right_bottom(), visible.left_bottom()] {
+                let uv = self.screen_to_uv(pos, flip_horizontal, flip_vertical, rotation);
+                // from the uv of the whole image to the uv inside the tile
+                let uv = ((uv - tile.uv.min) / tile.uv.size()).to_pos2();
+                mesh.vertices.push(Vertex { pos, uv, color: Color32::WHITE.gamma_multiply(self.opacity) });
+            }
+            Some(mesh)
+        }).collect()
     }
 
     fn highlight_hovered_pixel(&self, ui: &mut egui::Ui, image_rect: egui::Rect) {
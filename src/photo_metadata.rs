@@ -0,0 +1,269 @@
+use std::io::Cursor;
+
+use chrono::NaiveDateTime;
+use exif::{In, Tag, Value};
+use image::DynamicImage;
+
+/// How the camera was held, the EXIF Orientation tag.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Orientation {
+    #[default]
+    Normal,
+    FlipHorizontal,
+    Rotate180,
+    FlipVertical,
+    Transpose,
+    Rotate90,
+    Transverse,
+    Rotate270
+}
+
+impl Orientation {
+    pub fn from_tag(value: u32) -> Self {
+        match value {
+            2 => Orientation::FlipHorizontal,
+            3 => Orientation::Rotate180,
+            4 => Orientation::FlipVertical,
+            5 => Orientation::Transpose,
+            6 => Orientation::Rotate90,
+            7 => Orientation::Transverse,
+            8 => Orientation::Rotate270,
+            _ => Orientation::Normal
+        }
+    }
+
+    /// Transforms the stored pixels so the image is shown upright.
+    pub fn apply(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::Normal => image,
+            Orientation::FlipHorizontal => image.fliph(),
+            Orientation::Rotate180 => image.rotate180(),
+            Orientation::FlipVertical => image.flipv(),
+            Orientation::Transpose => image.rotate90().fliph(),
+            Orientation::Rotate90 => image.rotate90(),
+            Orientation::Transverse => image.rotate270().fliph(),
+            Orientation::Rotate270 => image.rotate270()
+        }
+    }
+}
+
+/// Metadata the camera embedded in the file, read from EXIF with XMP filling in the gaps.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct PhotoMetadata {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    // in seconds
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    // in millimeters
+    pub focal_length: Option<f64>,
+    pub iso: Option<u32>,
+    pub date_taken: Option<NaiveDateTime>,
+    // latitude and longitude in degrees
+    pub location: Option<(f64, f64)>,
+    pub orientation: Orientation
+}
+
+impl PhotoMetadata {
+    /// Returns `None` if the file has no EXIF or XMP metadata.
+    pub fn read(bytes: &[u8]) -> Option<Self> {
+        let exif = exif::Reader::new().read_from_container(&mut Cursor::new(bytes)).ok().map(|exif| from_exif(&exif));
+        let xmp = find_xmp_packet(bytes).map(from_xmp);
+        match (exif, xmp) {
+            (Some(exif), Some(xmp)) => Some(exif.or(xmp)),
+            (exif, xmp) => exif.or(xmp)
+        }
+    }
+
+    pub fn camera(&self) -> Option<String> {
+        match (self.make.as_deref(), self.model.as_deref()) {
+            // most cameras already start the model with the make
+            (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model.to_owned()),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (make, model) => make.or(model).map(str::to_owned)
+        }
+    }
+
+    pub fn exposure(&self) -> Option<String> {
+        let exposure_time = self.exposure_time.map(|time| {
+            if time < 1.0 && time > 0.0 {
+                format!("1/{:.0}s", 1.0 / time)
+            }
+            else {
+                format!("{time}s")
+            }
+        });
+        let f_number = self.f_number.map(|f_number| format!("f/{f_number:.1}"));
+        match (exposure_time, f_number) {
+            (Some(exposure_time), Some(f_number)) => Some(format!("{exposure_time} {f_number}")),
+            (exposure_time, f_number) => exposure_time.or(f_number)
+        }
+    }
+
+    // fills the missing fields from `other`
+    fn or(self, other: Self) -> Self {
+        Self {
+            make: self.make.or(other.make),
+            model: self.model.or(other.model),
+            lens: self.lens.or(other.lens),
+            exposure_time: self.exposure_time.or(other.exposure_time),
+            f_number: self.f_number.or(other.f_number),
+            focal_length: self.focal_length.or(other.focal_length),
+            iso: self.iso.or(other.iso),
+            date_taken: self.date_taken.or(other.date_taken),
+            location: self.location.or(other.location),
+            orientation: if self.orientation == Orientation::Normal { other.orientation } else { self.orientation }
+        }
+    }
+}
+
+fn from_exif(exif: &exif::Exif) -> PhotoMetadata {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let text = |tag: Tag| match field(tag)? {
+        Value::Ascii(values) => values.first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_owned())
+            .filter(|value| !value.is_empty()),
+        _ => None
+    };
+    let rational = |tag: Tag, index: usize| match field(tag)? {
+        Value::Rational(values) => values.get(index).map(|value| value.to_f64()),
+        _ => None
+    };
+    let coordinate = |tag: Tag, reference_tag: Tag| {
+        let degrees = rational(tag, 0)? + rational(tag, 1)? / 60.0 + rational(tag, 2)? / 3600.0;
+        let negative = text(reference_tag).is_some_and(|reference| reference == "S" || reference == "W");
+        Some(if negative { -degrees } else { degrees })
+    };
+    PhotoMetadata {
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        lens: text(Tag::LensModel),
+        exposure_time: rational(Tag::ExposureTime, 0),
+        f_number: rational(Tag::FNumber, 0),
+        focal_length: rational(Tag::FocalLength, 0),
+        iso: field(Tag::PhotographicSensitivity).and_then(|value| value.get_uint(0)),
+        date_taken: text(Tag::DateTimeOriginal)
+            .or_else(|| text(Tag::DateTime))
+            .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok()),
+        location: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef).zip(coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef)),
+        orientation: field(Tag::Orientation).and_then(|value| value.get_uint(0)).map(Orientation::from_tag).unwrap_or_default()
+    }
+}
+
+fn find_xmp_packet(bytes: &[u8]) -> Option<&str> {
+    const START: &[u8] = b"<x:xmpmeta";
+    const END: &[u8] = b"</x:xmpmeta>";
+    let start = bytes.windows(START.len()).position(|window| window == START)?;
+    let length = bytes[start..].windows(END.len()).position(|window| window == END)? + END.len();
+    std::str::from_utf8(&bytes[start..start + length]).ok()
+}
+
+fn from_xmp(packet: &str) -> PhotoMetadata {
+    let number = |name: &str| xmp_property(packet, name).and_then(|value| parse_xmp_number(&value));
+    let coordinate = |name: &str| xmp_property(packet, name).and_then(|value| parse_xmp_coordinate(&value));
+    PhotoMetadata {
+        make: xmp_property(packet, "tiff:Make"),
+        model: xmp_property(packet, "tiff:Model"),
+        lens: xmp_property(packet, "exifEX:LensModel").or_else(|| xmp_property(packet, "aux:Lens")),
+        exposure_time: number("exif:ExposureTime"),
+        f_number: number("exif:FNumber"),
+        focal_length: number("exif:FocalLength"),
+        iso: number("exif:ISOSpeedRatings").map(|iso| iso as u32),
+        date_taken: xmp_property(packet, "exif:DateTimeOriginal")
+            .or_else(|| xmp_property(packet, "xmp:CreateDate"))
+            // the time zone is ignored like it is for exif dates
+            .and_then(|date| NaiveDateTime::parse_from_str(date.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()),
+        location: coordinate("exif:GPSLatitude").zip(coordinate("exif:GPSLongitude")),
+        orientation: number("tiff:Orientation").map(|value| Orientation::from_tag(value as u32)).unwrap_or_default()
+    }
+}
+
+/// Finds a property written as an attribute `name="value"`, or as an element `<name>value</name>`.
+/// For lists the first item is returned.
+fn xmp_property(packet: &str, name: &str) -> Option<String> {
+    let value = if let Some(start) = packet.find(&format!("{name}=\"")) {
+        let value = &packet[start + name.len() + 2..];
+        &value[..value.find('"')?]
+    }
+    else {
+        let start = packet.find(&format!("<{name}>"))? + name.len() + 2;
+        let end = start + packet[start..].find(&format!("</{name}>"))?;
+        let mut value = &packet[start..end];
+        if let Some(item) = value.find("<rdf:li") {
+            value = &value[item..];
+            value = &value[value.find('>')? + 1..];
+            value = &value[..value.find('<')?];
+        }
+        value
+    };
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+// numbers are written as rationals like `1/250`
+fn parse_xmp_number(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator = denominator.parse::<f64>().ok().filter(|denominator| *denominator != 0.0)?;
+            Some(numerator.parse::<f64>().ok()? / denominator)
+        },
+        None => value.parse().ok()
+    }
+}
+
+// coordinates are written as `DDD,MM.mmk` or `DDD,MM,SSk` where k is the direction
+fn parse_xmp_coordinate(value: &str) -> Option<f64> {
+    let direction = value.chars().last()?;
+    let mut parts = value[..value.len() - direction.len_utf8()].split(',');
+    let degrees = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next().map_or(Some(0.0), |minutes| minutes.parse::<f64>().ok())?;
+    let seconds = parts.next().map_or(Some(0.0), |seconds| seconds.parse::<f64>().ok())?;
+    let coordinate = degrees + minutes / 60.0 + seconds / 3600.0;
+    match direction.to_ascii_uppercase() {
+        'N' | 'E' => Some(coordinate),
+        'S' | 'W' => Some(-coordinate),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description
+        tiff:Make="Canon" tiff:Model="Canon EOS 5D" exif:ExposureTime="1/250" exif:FNumber="28/10"
+        exif:DateTimeOriginal="2023-10-01T12:30:00+02:00" exif:GPSLatitude="32,4.8N" exif:GPSLongitude="34,46,48W" tiff:Orientation="6">
+        <exif:ISOSpeedRatings><rdf:Seq><rdf:li>400</rdf:li></rdf:Seq></exif:ISOSpeedRatings>
+        <aux:Lens>EF 50mm</aux:Lens>
+    </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+
+    #[test]
+    fn reads_xmp() {
+        let bytes = format!("junk before the packet {XMP} junk after");
+        let metadata = PhotoMetadata::read(bytes.as_bytes()).expect("The packet should be found.");
+        assert_eq!(metadata.camera().as_deref(), Some("Canon EOS 5D"));
+        assert_eq!(metadata.lens.as_deref(), Some("EF 50mm"));
+        assert_eq!(metadata.exposure().as_deref(), Some("1/250s f/2.8"));
+        assert_eq!(metadata.iso, Some(400));
+        assert_eq!(metadata.date_taken, NaiveDateTime::parse_from_str("2023-10-01 12:30:00", "%Y-%m-%d %H:%M:%S").ok());
+        let (latitude, longitude) = metadata.location.expect("The location is in the packet.");
+        assert!((latitude - 32.08).abs() < 1e-9);
+        assert!((longitude + 34.78).abs() < 1e-9);
+        assert_eq!(metadata.orientation, Orientation::Rotate90);
+    }
+
+    #[test]
+    fn orientation() {
+        let image = DynamicImage::new_rgba8(4, 2);
+        assert_eq!(Orientation::from_tag(6).apply(image.clone()).width(), 2);
+        assert_eq!(Orientation::from_tag(3).apply(image.clone()).width(), 4);
+        // pixel 0, 0 ends up in the top right corner when the image is rotated clockwise
+        let mut image = image.to_rgba8();
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        let rotated = Orientation::Rotate90.apply(DynamicImage::ImageRgba8(image.clone())).to_rgba8();
+        assert_eq!(rotated.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        let transposed = Orientation::Transpose.apply(DynamicImage::ImageRgba8(image)).to_rgba8();
+        assert_eq!(transposed.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,679 @@
+use std::{collections::HashMap, io::Cursor};
+
+use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageError, ImageFormat, ImageResult, Rgb, codecs::jpeg::JpegDecoder, error::{DecodingError, ImageFormatHint}};
+
+use crate::lossless_jpeg::LosslessJpeg;
+
+// the ifds of a file are a tree through the sub ifd tag, the limits protect against files where they loop
+const MAX_IFD_DEPTH: usize = 4;
+const MAX_IFD_COUNT: usize = 64;
+
+const TAG_WIDTH: u16 = 0x100;
+const TAG_HEIGHT: u16 = 0x101;
+const TAG_BITS_PER_SAMPLE: u16 = 0x102;
+const TAG_COMPRESSION: u16 = 0x103;
+const TAG_PHOTOMETRIC: u16 = 0x106;
+const TAG_MAKE: u16 = 0x10F;
+const TAG_STRIP_OFFSETS: u16 = 0x111;
+const TAG_SAMPLES_PER_PIXEL: u16 = 0x115;
+const TAG_ROWS_PER_STRIP: u16 = 0x116;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x117;
+const TAG_TILE_WIDTH: u16 = 0x142;
+const TAG_TILE_LENGTH: u16 = 0x143;
+const TAG_TILE_OFFSETS: u16 = 0x144;
+const TAG_TILE_BYTE_COUNTS: u16 = 0x145;
+const TAG_SUB_IFDS: u16 = 0x14A;
+const TAG_JPEG_OFFSET: u16 = 0x201;
+const TAG_JPEG_LENGTH: u16 = 0x202;
+const TAG_CFA_REPEAT_PATTERN_DIM: u16 = 0x828D;
+const TAG_CFA_PATTERN: u16 = 0x828E;
+const TAG_CR2_SLICES: u16 = 0xC640;
+const TAG_DNG_VERSION: u16 = 0xC612;
+const TAG_BLACK_LEVEL: u16 = 0xC61A;
+const TAG_WHITE_LEVEL: u16 = 0xC61D;
+const TAG_COLOR_MATRIX_1: u16 = 0xC621;
+const TAG_COLOR_MATRIX_2: u16 = 0xC622;
+const TAG_AS_SHOT_NEUTRAL: u16 = 0xC628;
+const TAG_CALIBRATION_ILLUMINANT_2: u16 = 0xC65B;
+const TAG_ACTIVE_AREA: u16 = 0xC68D;
+const TAG_SONY_BLACK_LEVEL: u16 = 0x7310;
+const TAG_SONY_WHITE_LEVEL: u16 = 0x787F;
+
+const COMPRESSION_NONE: u32 = 1;
+const COMPRESSION_OLD_JPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+const PHOTOMETRIC_CFA: u32 = 32803;
+const PHOTOMETRIC_LINEAR_RAW: u32 = 34892;
+const ILLUMINANT_D65: u32 = 21;
+
+// linear srgb to xyz, the color matrices in dng files map from xyz to the camera space
+const XYZ_FROM_SRGB: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.119192, 0.9503041]
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RawFormat {
+    Cr2,
+    Nef,
+    Arw,
+    Dng
+}
+
+impl RawFormat {
+    pub fn iterator() -> impl Iterator<Item = RawFormat> {
+        [RawFormat::Cr2, RawFormat::Nef, RawFormat::Arw, RawFormat::Dng].into_iter()
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        RawFormat::iterator().find(|format| format.extensions_str().iter().any(|format_ext| ext.eq_ignore_ascii_case(format_ext)))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RawFormat::Cr2 => "CR2",
+            RawFormat::Nef => "NEF",
+            RawFormat::Arw => "ARW",
+            RawFormat::Dng => "DNG"
+        }
+    }
+
+    pub fn extensions_str(&self) -> &'static [&'static str] {
+        match self {
+            RawFormat::Cr2 => &["cr2"],
+            RawFormat::Nef => &["nef"],
+            RawFormat::Arw => &["arw"],
+            RawFormat::Dng => &["dng"]
+        }
+    }
+
+    /// All of the raw formats are tiff files, they are told apart from plain tiff by their tags.
+    /// Nikon and Sony also make scanners and save in-camera tiffs, so only files with sensor data count for them.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        let tiff = Tiff::new(bytes)?;
+        let (ifd0, _) = tiff.read_ifd(tiff.first_ifd_offset()?)?;
+        if ifd0.contains_key(&TAG_DNG_VERSION) {
+            return Some(RawFormat::Dng);
+        }
+        if bytes.get(8..10) == Some(b"CR") {
+            return Some(RawFormat::Cr2);
+        }
+        let make = tiff.string(ifd0.get(&TAG_MAKE)?)?.to_uppercase();
+        if !tiff.ifds().iter().any(|ifd| tiff.value(ifd, TAG_PHOTOMETRIC).is_some_and(|photometric| photometric as u32 == PHOTOMETRIC_CFA)) {
+            return None;
+        }
+        if make.starts_with("NIKON") {
+            Some(RawFormat::Nef)
+        }
+        else if make.starts_with("SONY") {
+            Some(RawFormat::Arw)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Develops the sensor data if it's stored uncompressed or as a lossless jpeg like in most dng files.
+/// The compressed sensor data of cr2, nef and arw files isn't developed, their black levels and white balance
+/// are in the maker notes, the biggest preview the camera embedded is used for them instead.
+pub fn decode(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let tiff = Tiff::new(bytes).ok_or_else(|| decoding_error("The file isn't a tiff based raw file."))?;
+    let ifds = tiff.ifds();
+    if let Some(image) = ifds.iter().find_map(|ifd| tiff.develop(ifd)) {
+        return Ok(image);
+    }
+    let preview = tiff.largest_preview(&ifds).ok_or_else(|| decoding_error("The raw data is compressed and there is no embedded preview."))?;
+    image::load_from_memory_with_format(preview, ImageFormat::Jpeg)
+}
+
+fn decoding_error(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("RAW")), message))
+}
+
+struct Entry {
+    kind: u16,
+    count: usize,
+    // where the value is, it's inside the entry if it fits in 4 bytes
+    data_offset: usize
+}
+
+type Ifd = HashMap<u16, Entry>;
+
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    big_endian: bool
+}
+
+impl<'a> Tiff<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        let big_endian = match bytes.get(0..4)? {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            _ => return None
+        };
+        Some(Self { bytes, big_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn first_ifd_offset(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    /// Returns the ifd and the offset of the next one in the chain.
+    fn read_ifd(&self, offset: usize) -> Option<(Ifd, usize)> {
+        let count = self.u16_at(offset)? as usize;
+        let mut ifd = Ifd::new();
+        for index in 0..count {
+            let entry_offset = offset + 2 + index * 12;
+            let kind = self.u16_at(entry_offset + 2)?;
+            let count = self.u32_at(entry_offset + 4)? as usize;
+            let size = type_size(kind).checked_mul(count)?;
+            let data_offset = if size <= 4 { entry_offset + 8 } else { self.u32_at(entry_offset + 8)? as usize };
+            ifd.insert(self.u16_at(entry_offset)?, Entry { kind, count, data_offset });
+        }
+        let next = self.u32_at(offset + 2 + count * 12).unwrap_or(0) as usize;
+        Some((ifd, next))
+    }
+
+    /// The ifd chain and all the sub ifds.
+    fn ifds(&self) -> Vec<Ifd> {
+        let mut ifds = Vec::new();
+        let mut pending: Vec<(usize, usize)> = Vec::new();
+        let mut offset = self.first_ifd_offset().unwrap_or(0);
+        while offset != 0 && pending.len() < MAX_IFD_COUNT {
+            pending.push((offset, 0));
+            offset = self.read_ifd(offset).map(|(_, next)| next).unwrap_or(0);
+        }
+        while let Some((offset, depth)) = pending.pop() {
+            if ifds.len() >= MAX_IFD_COUNT {
+                break;
+            }
+            if let Some((ifd, _)) = self.read_ifd(offset) {
+                if depth < MAX_IFD_DEPTH {
+                    let sub_ifds = ifd.get(&TAG_SUB_IFDS).and_then(|entry| self.values(entry)).unwrap_or_default();
+                    pending.extend(sub_ifds.into_iter().map(|offset| (offset as usize, depth + 1)));
+                }
+                ifds.push(ifd);
+            }
+        }
+        ifds
+    }
+
+    fn values(&self, entry: &Entry) -> Option<Vec<f64>> {
+        let size = type_size(entry.kind);
+        (0..entry.count)
+            .map(|index| {
+                let offset = entry.data_offset.checked_add(index.checked_mul(size)?)?;
+                match entry.kind {
+                    1 | 7 => self.bytes.get(offset).map(|byte| *byte as f64),
+                    3 => self.u16_at(offset).map(|value| value as f64),
+                    4 | 13 => self.u32_at(offset).map(|value| value as f64),
+                    9 => self.u32_at(offset).map(|value| value as i32 as f64),
+                    5 => Some(self.u32_at(offset)? as f64 / self.u32_at(offset + 4)?.max(1) as f64),
+                    10 => Some(self.u32_at(offset)? as i32 as f64 / (self.u32_at(offset + 4)? as i32 as f64)),
+                    _ => None
+                }
+            })
+            .collect()
+    }
+
+    fn value(&self, ifd: &Ifd, tag: u16) -> Option<f64> {
+        self.values(ifd.get(&tag)?)?.first().copied()
+    }
+
+    fn string(&self, entry: &Entry) -> Option<String> {
+        let bytes = self.bytes.get(entry.data_offset..entry.data_offset.checked_add(entry.count)?)?;
+        Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').to_owned())
+    }
+
+    fn largest_preview(&self, ifds: &[Ifd]) -> Option<&'a [u8]> {
+        ifds.iter()
+            .filter_map(|ifd| {
+                let (offset, length) = match (self.value(ifd, TAG_JPEG_OFFSET), self.value(ifd, TAG_JPEG_LENGTH)) {
+                    (Some(offset), Some(length)) => (offset, length),
+                    _ => {
+                        let compression = self.value(ifd, TAG_COMPRESSION)? as u32;
+                        let photometric = self.value(ifd, TAG_PHOTOMETRIC).unwrap_or(0.0) as u32;
+                        // the sensor data of some formats is a lossless jpeg, it isn't a preview
+                        let sensor_data = photometric == PHOTOMETRIC_CFA || photometric == PHOTOMETRIC_LINEAR_RAW || ifd.contains_key(&TAG_CR2_SLICES);
+                        if !matches!(compression, COMPRESSION_OLD_JPEG | COMPRESSION_JPEG) || sensor_data {
+                            return None;
+                        }
+                        (self.value(ifd, TAG_STRIP_OFFSETS)?, self.value(ifd, TAG_STRIP_BYTE_COUNTS)?)
+                    }
+                };
+                let start = offset as usize;
+                let jpeg = self.bytes.get(start..start.checked_add(length as usize)?)?;
+                let (width, height) = JpegDecoder::new(Cursor::new(jpeg)).ok()?.dimensions();
+                Some((jpeg, width as u64 * height as u64))
+            })
+            .max_by_key(|(_, pixels)| *pixels)
+            .map(|(jpeg, _)| jpeg)
+    }
+
+    /// Demosaics uncompressed bayer data, `None` if the ifd doesn't hold data that can be developed.
+    fn develop(&self, ifd: &Ifd) -> Option<DynamicImage> {
+        let compression = self.value(ifd, TAG_COMPRESSION)? as u32;
+        let is_cfa = self.value(ifd, TAG_PHOTOMETRIC)? as u32 == PHOTOMETRIC_CFA
+            && matches!(compression, COMPRESSION_NONE | COMPRESSION_JPEG)
+            && self.value(ifd, TAG_SAMPLES_PER_PIXEL).unwrap_or(1.0) as u32 == 1;
+        if !is_cfa {
+            return None;
+        }
+        let width = self.value(ifd, TAG_WIDTH)? as usize;
+        let height = self.value(ifd, TAG_HEIGHT)? as usize;
+        let bits = self.value(ifd, TAG_BITS_PER_SAMPLE)? as u32;
+        let samples = if compression == COMPRESSION_JPEG {
+            self.read_lossless_jpeg(ifd, width, height)?
+        } else { self.read_samples(ifd, width, height, bits)? };
+
+        let pattern = self.cfa_pattern(ifd)?;
+        let black = ifd.get(&TAG_BLACK_LEVEL).or(ifd.get(&TAG_SONY_BLACK_LEVEL))
+            .and_then(|entry| self.values(entry))
+            .filter(|levels| !levels.is_empty())
+            .map(|levels| levels.iter().sum::<f64>() / levels.len() as f64)
+            .unwrap_or(0.0) as f32;
+        let white = self.value(ifd, TAG_WHITE_LEVEL)
+            .or_else(|| self.value(ifd, TAG_SONY_WHITE_LEVEL))
+            .unwrap_or(((1u32 << bits.min(16)) - 1) as f64) as f32;
+        let area = match ifd.get(&TAG_ACTIVE_AREA).and_then(|entry| self.values(entry)) {
+            Some(area) if area.len() == 4 => [area[0] as usize, area[1] as usize, (area[2] as usize).min(height), (area[3] as usize).min(width)],
+            _ => [0, 0, height, width]
+        };
+        if area[0] >= area[2] || area[1] >= area[3] {
+            return None;
+        }
+        let cfa = Cfa { width, samples, pattern, origin: [area[0], area[1]], black, range: (white - black).max(1.0) };
+
+        // the as shot neutral is the camera color of white, the inverse of it are the white balance multipliers
+        let white_balance = self.dng_tag(ifd, TAG_AS_SHOT_NEUTRAL)
+            .filter(|neutral| neutral.len() == 3 && neutral.iter().all(|value| *value > 0.0))
+            .map(|neutral| [(neutral[1] / neutral[0]) as f32, 1.0, (neutral[1] / neutral[2]) as f32])
+            .unwrap_or_else(|| cfa.gray_world(area));
+        let camera_to_srgb = self.color_matrix(ifd).and_then(camera_to_srgb).unwrap_or(IDENTITY);
+        Some(DynamicImage::ImageRgb16(cfa.develop(area, white_balance, camera_to_srgb)))
+    }
+
+    fn ifd0(&self) -> Option<Ifd> {
+        self.read_ifd(self.first_ifd_offset()?).map(|(ifd, _)| ifd)
+    }
+
+    /// Dng stores the color tags in the first ifd while the sensor data is usually in a sub ifd.
+    fn dng_tag(&self, ifd: &Ifd, tag: u16) -> Option<Vec<f64>> {
+        match ifd.get(&tag) {
+            Some(entry) => self.values(entry),
+            None => self.values(self.ifd0()?.get(&tag)?)
+        }
+    }
+
+    fn color_matrix(&self, ifd: &Ifd) -> Option<Vec<f64>> {
+        // the second matrix is usually for daylight, it's closer to most photos than the first one
+        let daylight = self.dng_tag(ifd, TAG_CALIBRATION_ILLUMINANT_2)
+            .is_some_and(|illuminant| illuminant.first().is_some_and(|illuminant| *illuminant as u32 == ILLUMINANT_D65));
+        let matrix = if daylight { self.dng_tag(ifd, TAG_COLOR_MATRIX_2) } else { None };
+        matrix.or_else(|| self.dng_tag(ifd, TAG_COLOR_MATRIX_1)).filter(|matrix| matrix.len() == 9)
+    }
+
+    fn cfa_pattern(&self, ifd: &Ifd) -> Option<[[usize; 2]; 2]> {
+        let dimensions = ifd.get(&TAG_CFA_REPEAT_PATTERN_DIM).and_then(|entry| self.values(entry));
+        let pattern = ifd.get(&TAG_CFA_PATTERN).and_then(|entry| self.values(entry));
+        match (dimensions.as_deref(), pattern) {
+            (Some([2.0, 2.0]) | None, Some(pattern)) if pattern.len() == 4 && pattern.iter().all(|color| *color <= 2.0) => {
+                Some([[pattern[0] as usize, pattern[1] as usize], [pattern[2] as usize, pattern[3] as usize]])
+            },
+            // other patterns like x-trans aren't supported
+            (_, Some(_)) => None,
+            // rggb is the most common layout
+            (_, None) => Some([[0, 1], [1, 2]])
+        }
+    }
+
+    /// Decodes every tile or strip, they are separate lossless jpegs.
+    fn read_lossless_jpeg(&self, ifd: &Ifd, width: usize, height: usize) -> Option<Vec<u16>> {
+        let pixel_count = width.checked_mul(height)?;
+        // a lossless jpeg is at least a few times smaller than the samples
+        if pixel_count / 8 > self.bytes.len() {
+            return None;
+        }
+        let (offsets, byte_counts, chunk_width, chunk_height) = match ifd.get(&TAG_TILE_OFFSETS) {
+            Some(offsets) => {
+                let (tile_width, tile_height) = (self.value(ifd, TAG_TILE_WIDTH)? as usize, self.value(ifd, TAG_TILE_LENGTH)? as usize);
+                (self.values(offsets)?, self.values(ifd.get(&TAG_TILE_BYTE_COUNTS)?)?, tile_width, tile_height)
+            },
+            None => {
+                let rows = self.value(ifd, TAG_ROWS_PER_STRIP).map_or(height, |rows| rows as usize);
+                (self.values(ifd.get(&TAG_STRIP_OFFSETS)?)?, self.values(ifd.get(&TAG_STRIP_BYTE_COUNTS)?)?, width, rows)
+            }
+        };
+        if chunk_width == 0 || chunk_height == 0 {
+            return None;
+        }
+        let chunks_across = width.div_ceil(chunk_width);
+        let mut samples = vec![0; pixel_count];
+        for (index, (offset, byte_count)) in offsets.iter().zip(byte_counts).enumerate() {
+            let start = *offset as usize;
+            let jpeg = LosslessJpeg::decode(self.bytes.get(start..start.checked_add(byte_count as usize)?)?)?;
+            let (chunk_x, chunk_y) = (index % chunks_across * chunk_width, index / chunks_across * chunk_height);
+            let row_length = jpeg.width * jpeg.components;
+            // the edge tiles are padded past the image
+            for y in 0..jpeg.height.min(chunk_height).min(height.saturating_sub(chunk_y)) {
+                let columns = row_length.min(chunk_width).min(width.saturating_sub(chunk_x));
+                let source = &jpeg.samples[y * row_length..y * row_length + columns];
+                let target = (chunk_y + y) * width + chunk_x;
+                samples[target..target + columns].copy_from_slice(source);
+            }
+        }
+        Some(samples)
+    }
+
+    /// Reads the samples of strips or tiles into one row major buffer.
+    fn read_samples(&self, ifd: &Ifd, width: usize, height: usize, bits: u32) -> Option<Vec<u16>> {
+        // 12 and 14 bit samples are supported when they are stored in 16 bits, tightly packed ones aren't
+        let sample_size = match bits {
+            8 => 1,
+            9..=16 => 2,
+            _ => return None
+        };
+        let read = |offset: usize| -> Option<u16> {
+            if sample_size == 1 { self.bytes.get(offset).map(|byte| *byte as u16) } else { self.u16_at(offset) }
+        };
+        let pixel_count = width.checked_mul(height)?;
+        if pixel_count.checked_mul(sample_size)? > self.bytes.len() {
+            return None;
+        }
+        let mut samples = vec![0; pixel_count];
+        if let Some(tile_offsets) = ifd.get(&TAG_TILE_OFFSETS).and_then(|entry| self.values(entry)) {
+            let tile_width = self.value(ifd, TAG_TILE_WIDTH)? as usize;
+            let tile_height = self.value(ifd, TAG_TILE_LENGTH)? as usize;
+            if tile_width == 0 || tile_height == 0 {
+                return None;
+            }
+            let tiles_across = width.div_ceil(tile_width);
+            for (index, offset) in tile_offsets.iter().enumerate() {
+                let (tile_x, tile_y) = (index % tiles_across * tile_width, index / tiles_across * tile_height);
+                for y in tile_y..(tile_y + tile_height).min(height) {
+                    for x in tile_x..(tile_x + tile_width).min(width) {
+                        let position = ((y - tile_y) * tile_width + x - tile_x) * sample_size;
+                        samples[y * width + x] = read(*offset as usize + position)?;
+                    }
+                }
+            }
+        }
+        else {
+            let strip_offsets = self.values(ifd.get(&TAG_STRIP_OFFSETS)?)?;
+            let strip_byte_counts = self.values(ifd.get(&TAG_STRIP_BYTE_COUNTS)?)?;
+            let mut index = 0;
+            for (offset, byte_count) in strip_offsets.iter().zip(strip_byte_counts) {
+                for position in (0..byte_count as usize).step_by(sample_size) {
+                    if index == pixel_count {
+                        break;
+                    }
+                    samples[index] = read(*offset as usize + position)?;
+                    index += 1;
+                }
+            }
+            if index < pixel_count {
+                return None;
+            }
+        }
+        Some(samples)
+    }
+}
+
+fn type_size(kind: u16) -> usize {
+    match kind {
+        3 | 8 => 2,
+        4 | 9 | 11 | 13 => 4,
+        5 | 10 | 12 => 8,
+        _ => 1
+    }
+}
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Turns the xyz to camera matrix of a dng file into a camera to srgb matrix that keeps white as white.
+fn camera_to_srgb(xyz_to_camera: Vec<f64>) -> Option<[[f32; 3]; 3]> {
+    let mut srgb_to_camera = [[0.0; 3]; 3];
+    for row in 0..3 {
+        for column in 0..3 {
+            srgb_to_camera[row][column] = (0..3).map(|index| xyz_to_camera[row * 3 + index] as f32 * XYZ_FROM_SRGB[index][column]).sum();
+        }
+        let sum: f32 = srgb_to_camera[row].iter().sum();
+        if sum.abs() < f32::EPSILON {
+            return None;
+        }
+        srgb_to_camera[row] = srgb_to_camera[row].map(|value| value / sum);
+    }
+    invert(srgb_to_camera)
+}
+
+fn invert(matrix: [[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let [[a, b, c], [d, e, f], [g, h, i]] = matrix;
+    let determinant = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = [
+        [e * i - f * h, c * h - b * i, b * f - c * e],
+        [f * g - d * i, a * i - c * g, c * d - a * f],
+        [d * h - e * g, b * g - a * h, a * e - b * d]
+    ];
+    Some(inverse.map(|row| row.map(|value| value / determinant)))
+}
+
+struct Cfa {
+    width: usize,
+    samples: Vec<u16>,
+    // the color of every position in the 2x2 pattern, 0 is red, 1 is green and 2 is blue
+    pattern: [[usize; 2]; 2],
+    // the top and left of the active area, the pattern starts there and not at the corner of the sensor
+    origin: [usize; 2],
+    black: f32,
+    range: f32
+}
+
+impl Cfa {
+    fn color(&self, x: usize, y: usize) -> usize {
+        self.pattern[(y - self.origin[0]) % 2][(x - self.origin[1]) % 2]
+    }
+
+    fn value(&self, x: usize, y: usize) -> f32 {
+        ((self.samples[y * self.width + x] as f32 - self.black) / self.range).max(0.0)
+    }
+
+    /// White balance multipliers that make the average color gray, for files without as shot white balance.
+    fn gray_world(&self, [top, left, bottom, right]: [usize; 4]) -> [f32; 3] {
+        let mut sums = [0.0f64; 3];
+        // every pixel isn't needed for an average
+        for y in (top..bottom).step_by(3) {
+            for x in (left..right).step_by(3) {
+                sums[self.color(x, y)] += self.value(x, y) as f64;
+            }
+        }
+        if sums.iter().any(|sum| *sum <= 0.0) {
+            return [1.0; 3];
+        }
+        [(sums[1] / sums[0]) as f32, 1.0, (sums[1] / sums[2]) as f32]
+    }
+
+    /// Bilinear demosaicing, the missing colors of a pixel are the average of its neighbours with that color.
+    fn develop(&self, [top, left, bottom, right]: [usize; 4], white_balance: [f32; 3], camera_to_srgb: [[f32; 3]; 3]) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+        let gamma: Vec<u16> = (0..=u16::MAX).map(|value| (srgb_gamma(value as f32 / u16::MAX as f32) * u16::MAX as f32).round() as u16).collect();
+        ImageBuffer::from_fn((right - left) as u32, (bottom - top) as u32, |x, y| {
+            let (x, y) = (x as usize + left, y as usize + top);
+            let mut sums = [0.0; 3];
+            let mut counts = [0; 3];
+            for neighbour_y in y.saturating_sub(1).max(top)..(y + 2).min(bottom) {
+                for neighbour_x in x.saturating_sub(1).max(left)..(x + 2).min(right) {
+                    let color = self.color(neighbour_x, neighbour_y);
+                    sums[color] += self.value(neighbour_x, neighbour_y);
+                    counts[color] += 1;
+                }
+            }
+            let mut camera = [0.0; 3];
+            for color in 0..3 {
+                camera[color] = if self.color(x, y) == color {
+                    self.value(x, y)
+                }
+                else if counts[color] > 0 {
+                    sums[color] / counts[color] as f32
+                }
+                else { 0.0 };
+                // clipping after the white balance keeps blown highlights white instead of pink
+                camera[color] = (camera[color] * white_balance[color]).min(1.0);
+            }
+            Rgb(camera_to_srgb.map(|row| {
+                let linear = (row[0] * camera[0] + row[1] * camera[1] + row[2] * camera[2]).clamp(0.0, 1.0);
+                gamma[(linear * u16::MAX as f32).round() as usize]
+            }))
+        })
+    }
+}
+
+fn srgb_gamma(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    }
+    else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little endian tiff with one ifd, the pixel data is placed right after the header.
+    fn tiff(entries: &[(u16, u16, Vec<u8>)], data: &[u8]) -> Vec<u8> {
+        let ifd_offset = 8 + data.len();
+        let mut extra_offset = ifd_offset + 2 + entries.len() * 12 + 4;
+        let mut bytes = b"II*\0".to_vec();
+        bytes.extend_from_slice(&(ifd_offset as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut extra = Vec::new();
+        for (tag, kind, value) in entries {
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(&((value.len() / type_size(*kind)) as u32).to_le_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.clone();
+                inline.resize(4, 0);
+                bytes.extend_from_slice(&inline);
+            }
+            else {
+                bytes.extend_from_slice(&(extra_offset as u32).to_le_bytes());
+                extra_offset += value.len();
+                extra.extend_from_slice(value);
+            }
+        }
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&extra);
+        bytes
+    }
+
+    fn short(value: u16) -> Vec<u8> {
+        value.to_le_bytes().to_vec()
+    }
+
+    fn long(value: u32) -> Vec<u8> {
+        value.to_le_bytes().to_vec()
+    }
+
+    #[test]
+    fn develops_uncompressed_dng() {
+        // an rggb sensor that saw a gray surface through a green tinted light
+        let data: Vec<u8> = (0..16)
+            .flat_map(|index| {
+                let (x, y) = (index % 4, index / 4);
+                let value: u16 = if x % 2 != y % 2 { 2000 } else { 1000 };
+                value.to_le_bytes()
+            })
+            .collect();
+        let neutral: Vec<u8> = [(1, 2), (1, 1), (1, 2)].iter().flat_map(|(numerator, denominator): &(u32, u32)| {
+            numerator.to_le_bytes().into_iter().chain(denominator.to_le_bytes())
+        }).collect();
+        let bytes = tiff(&[
+            (TAG_WIDTH, 3, short(4)),
+            (TAG_HEIGHT, 3, short(4)),
+            (TAG_BITS_PER_SAMPLE, 3, short(16)),
+            (TAG_COMPRESSION, 3, short(1)),
+            (TAG_PHOTOMETRIC, 3, short(PHOTOMETRIC_CFA as u16)),
+            (TAG_STRIP_OFFSETS, 4, long(8)),
+            (TAG_STRIP_BYTE_COUNTS, 4, long(data.len() as u32)),
+            (TAG_CFA_REPEAT_PATTERN_DIM, 3, [short(2), short(2)].concat()),
+            (TAG_CFA_PATTERN, 1, vec![0, 1, 1, 2]),
+            (TAG_DNG_VERSION, 1, vec![1, 4, 0, 0]),
+            (TAG_WHITE_LEVEL, 3, short(4000)),
+            (TAG_AS_SHOT_NEUTRAL, 5, neutral)
+        ], &data);
+        assert_eq!(RawFormat::detect(&bytes), Some(RawFormat::Dng));
+        let image = decode(&bytes).expect("The raw data is uncompressed.").to_rgb16();
+        assert_eq!(image.dimensions(), (4, 4));
+        let [red, green, blue] = image.get_pixel(1, 1).0;
+        assert_eq!(red, green);
+        assert_eq!(green, blue);
+        assert!(red > 0 && red < u16::MAX);
+    }
+
+    #[test]
+    fn pattern_starts_at_the_active_area() {
+        // a masked border on the top and left, the rggb pattern starts at 1, 1
+        let data: Vec<u8> = (0..25)
+            .flat_map(|index| {
+                let (x, y) = (index % 5, index / 5);
+                let value: u16 = match (x, y) {
+                    (0, _) | (_, 0) => 0,
+                    (x, y) if x % 2 == 1 && y % 2 == 1 => 3000,
+                    (x, y) if x % 2 == 0 && y % 2 == 0 => 1000,
+                    _ => 2000
+                };
+                value.to_le_bytes()
+            })
+            .collect();
+        let neutral: Vec<u8> = [1u32, 1, 1, 1, 1, 1].iter().flat_map(|value| value.to_le_bytes()).collect();
+        let bytes = tiff(&[
+            (TAG_WIDTH, 3, short(5)),
+            (TAG_HEIGHT, 3, short(5)),
+            (TAG_BITS_PER_SAMPLE, 3, short(16)),
+            (TAG_COMPRESSION, 3, short(1)),
+            (TAG_PHOTOMETRIC, 3, short(PHOTOMETRIC_CFA as u16)),
+            (TAG_STRIP_OFFSETS, 4, long(8)),
+            (TAG_STRIP_BYTE_COUNTS, 4, long(data.len() as u32)),
+            (TAG_CFA_REPEAT_PATTERN_DIM, 3, [short(2), short(2)].concat()),
+            (TAG_CFA_PATTERN, 1, vec![0, 1, 1, 2]),
+            (TAG_DNG_VERSION, 1, vec![1, 4, 0, 0]),
+            (TAG_WHITE_LEVEL, 3, short(4000)),
+            (TAG_ACTIVE_AREA, 3, [short(1), short(1), short(5), short(5)].concat()),
+            (TAG_AS_SHOT_NEUTRAL, 5, neutral)
+        ], &data);
+        let image = decode(&bytes).expect("The raw data is uncompressed.").to_rgb16();
+        assert_eq!(image.dimensions(), (4, 4));
+        let [red, green, blue] = image.get_pixel(1, 1).0;
+        assert!(red > green && green > blue);
+    }
+
+    #[test]
+    fn detects_raw_formats() {
+        assert_eq!(RawFormat::from_extension("NEF"), Some(RawFormat::Nef));
+        let make = b"SONY\0".to_vec();
+        let bytes = tiff(&[(TAG_MAKE, 2, make.clone()), (TAG_PHOTOMETRIC, 3, short(PHOTOMETRIC_CFA as u16))], &[]);
+        assert_eq!(RawFormat::detect(&bytes), Some(RawFormat::Arw));
+        // an in-camera tiff or a scan
+        let bytes = tiff(&[(TAG_MAKE, 2, make), (TAG_PHOTOMETRIC, 3, short(2))], &[]);
+        assert_eq!(RawFormat::detect(&bytes), None);
+        let bytes = tiff(&[(TAG_WIDTH, 3, short(4))], &[]);
+        assert_eq!(RawFormat::detect(&bytes), None);
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const MAX_RECENT_FILES: usize = 12;
+
+/// The images that were opened lately, the most recent one is first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>
+}
+
+impl RecentFiles {
+    pub fn load(ctx: &egui::Context) -> Self {
+        ctx.data_mut(|reader| {
+            reader.get_persisted(egui::Id::new("recent_files"))
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn store(&self, ctx: &egui::Context) {
+        ctx.data_mut(|writer| {
+            writer.insert_persisted(egui::Id::new("recent_files"), self.clone());
+        });
+    }
+
+    /// Moves the path to the front, returns true if the list changed.
+    pub fn add(&mut self, path: &Path) -> bool {
+        if self.paths.first().is_some_and(|first| first == path) {
+            return false;
+        }
+        self.paths.retain(|recent| recent != path);
+        self.paths.insert(0, path.to_path_buf());
+        self.paths.truncate(MAX_RECENT_FILES);
+        true
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|recent| recent != path);
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+/// The image that was open when the app was closed and how it was viewed.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastSession {
+    pub path: PathBuf,
+    // used when the file is gone, the image at the same place in the directory is opened instead
+    pub index: usize,
+    pub scale: f32,
+    pub offset: egui::Vec2,
+    pub rotation: usize,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool
+}
+
+impl LastSession {
+    pub fn load(ctx: &egui::Context) -> Option<Self> {
+        ctx.data_mut(|reader| reader.get_persisted(egui::Id::new("last_session")))
+    }
+
+    pub fn store(&self, ctx: &egui::Context) {
+        ctx.data_mut(|writer| {
+            writer.insert_persisted(egui::Id::new("last_session"), self.clone());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_files_order() {
+        let mut recent = RecentFiles::default();
+        assert!(recent.add(Path::new("/a.png")));
+        assert!(recent.add(Path::new("/b.png")));
+        assert!(!recent.add(Path::new("/b.png")));
+        assert!(recent.add(Path::new("/a.png")));
+        assert_eq!(recent.paths(), [PathBuf::from("/a.png"), PathBuf::from("/b.png")]);
+        for index in 0..MAX_RECENT_FILES {
+            recent.add(&PathBuf::from(format!("/{index}.png")));
+        }
+        assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+    }
+}
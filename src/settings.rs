@@ -1,7 +1,8 @@
-use crate::{theme::{ThemeKind, Theme}, widgets::{RadioValue, select, key_bind_widget, KeyboardShortcutRef, switch}};
+use crate::{theme::{ThemeKind, Theme}, sort_order::SortOrder, slideshow::{SlideshowOptions, Transition}, widgets::{RadioValue, select, key_bind_widget, KeyboardShortcutRef, switch}};
 use serde::{Serialize, Deserialize};
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyBinds {
     #[serde(with = "KeyboardShortcutRef")]
     pub next_image: egui::KeyboardShortcut,
@@ -22,7 +23,21 @@ pub struct KeyBinds {
     #[serde(with = "KeyboardShortcutRef")]
     pub flip_vertical: egui::KeyboardShortcut,
     #[serde(with = "KeyboardShortcutRef")]
-    pub pick_color: egui::KeyboardShortcut
+    pub pick_color: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub toggle_playback: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub next_frame: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub previous_frame: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub paste_image: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub copy_image: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub toggle_slideshow: egui::KeyboardShortcut,
+    #[serde(with = "KeyboardShortcutRef")]
+    pub pause_slideshow: egui::KeyboardShortcut
 }
 
 impl Default for KeyBinds {
@@ -37,32 +52,71 @@ impl Default for KeyBinds {
             rotate: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::R), 
             flip_horizontal: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::H), 
             flip_vertical: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::V),
-            pick_color: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::P)
+            pick_color: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::P),
+            toggle_playback: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Space),
+            next_frame: egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::ArrowRight),
+            previous_frame: egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::ArrowLeft),
+            paste_image: egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::V),
+            copy_image: egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::C),
+            toggle_slideshow: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F5),
+            // space already plays and pauses animations
+            pause_slideshow: egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::K)
         }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub image_filtering: egui::TextureFilter,
     pub theme: ThemeKind,
     pub key_binds: KeyBinds,
-    pub experimental_features: bool
+    pub experimental_features: bool,
+    // in megabytes
+    pub cache_memory_budget: usize,
+    // how many images before and after the current one are decoded ahead of time
+    pub preload_count: usize,
+    // open the last image at the same zoom, pan, rotation and flip when the app starts without paths
+    pub restore_session: bool,
+    // these are changed from the bottom bar
+    pub sort_order: SortOrder,
+    pub include_subfolders: bool,
+    pub show_filmstrip: bool,
+    pub gallery_tile_size: f32,
+    pub slideshow: SlideshowOptions
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self { 
+            image_filtering: egui::TextureFilter::Nearest,
+            theme: ThemeKind::default(),
+            key_binds: KeyBinds::default(),
+            experimental_features: false,
+            cache_memory_budget: 512,
+            preload_count: 2,
+            restore_session: false,
+            sort_order: SortOrder::default(),
+            include_subfolders: false,
+            show_filmstrip: false,
+            gallery_tile_size: 128.0,
+            slideshow: SlideshowOptions::default()
+        }
+    }
 }
 
 impl Settings {
     pub fn load(ctx: &egui::Context) -> Self {
         ctx.data_mut(|reader| {
             reader.get_persisted(egui::Id::new("settings"))
-                .unwrap_or(Self { 
-                    image_filtering: egui::TextureFilter::Nearest,
-                    theme: ThemeKind::default(),
-                    key_binds: KeyBinds::default(),
-                    experimental_features: false
-                })
+                .unwrap_or_default()
         })
     }
 
+    pub fn cache_budget_bytes(&self) -> usize {
+        self.cache_memory_budget * 1024 * 1024
+    }
+
     pub fn store(&self, ctx: &egui::Context) {
         ctx.data_mut(|writer| {
             writer.insert_persisted(egui::Id::new("settings"), self.clone()); 
@@ -92,6 +146,29 @@ impl Settings {
                         update = Some(SettingsUpdate::ReloadTexture);
                     }
             
+                    ui.label(egui::RichText::new("Performance").text_style(theme.heading2()));
+                    egui::Grid::new("performance_grid").show(ui, |ui| {
+                        ui.label("Image cache memory");
+                        let res = ui.add(egui::DragValue::new(&mut self.cache_memory_budget).clamp_range(0..=16384).suffix(" MB"));
+                        if res.changed() {
+                            update = Some(SettingsUpdate::ResizeCache);
+                        }
+                        ui.end_row();
+
+                        ui.label("Preloaded images");
+                        ui.add(egui::DragValue::new(&mut self.preload_count).clamp_range(0..=10))
+                            .on_hover_text("How many images before and after the current one are loaded ahead of time.");
+                        ui.end_row();
+                    });
+
+                    ui.label(egui::RichText::new("Startup").text_style(theme.heading2()));
+                    ui.horizontal(|ui| {
+                        ui.add(switch(&mut self.restore_session));
+                        ui.label("Reopen the last image");
+                    });
+
+                    self.slideshow(ui, theme);
+
                     self.key_binds(ui, theme);
         
                     ui.horizontal(|ui| {
@@ -111,6 +188,27 @@ impl Settings {
         update
     }
 
+    fn slideshow(&mut self, ui: &mut egui::Ui, theme: &Theme) {
+        ui.label(egui::RichText::new("Slideshow").text_style(theme.heading2()));
+        ui.horizontal(|ui| {
+            ui.label("Interval");
+            ui.add(egui::DragValue::new(&mut self.slideshow.interval).clamp_range(0.5..=3600.0).speed(0.1).suffix(" s"));
+        });
+        ui.label("Transition");
+        select(ui, "transition_select", &mut self.slideshow.transition, vec![RadioValue::new("Cut", Transition::Cut), RadioValue::new("Crossfade", Transition::Crossfade)]);
+        ui.horizontal(|ui| {
+            ui.add(switch(&mut self.slideshow.shuffle));
+            ui.label("Shuffle");
+        });
+        ui.horizontal(|ui| {
+            ui.add(switch(&mut self.slideshow.repeat));
+            ui.label("Start over after the last image");
+        });
+        ui.horizontal(|ui| {
+            ui.add(switch(&mut self.slideshow.pause_on_hover));
+            ui.label("Pause while the pointer is over the image");
+        });
+    }
 
     fn key_binds(&mut self, ui: &mut egui::Ui, theme: &Theme) {
         ui.label(egui::RichText::new("Key binds").text_style(theme.heading2()));
@@ -127,6 +225,13 @@ impl Settings {
             key_bind_widget(ui, "Rotate", &mut self.key_binds.rotate, default_key_binds.rotate);
             key_bind_widget(ui, "Flip horizontal", &mut self.key_binds.flip_horizontal, default_key_binds.flip_horizontal);
             key_bind_widget(ui, "Flip vertical", &mut self.key_binds.flip_vertical, default_key_binds.flip_vertical);
+            key_bind_widget(ui, "Play/pause animation", &mut self.key_binds.toggle_playback, default_key_binds.toggle_playback);
+            key_bind_widget(ui, "Next frame", &mut self.key_binds.next_frame, default_key_binds.next_frame);
+            key_bind_widget(ui, "Previous frame", &mut self.key_binds.previous_frame, default_key_binds.previous_frame);
+            key_bind_widget(ui, "Paste image", &mut self.key_binds.paste_image, default_key_binds.paste_image);
+            key_bind_widget(ui, "Copy image", &mut self.key_binds.copy_image, default_key_binds.copy_image);
+            key_bind_widget(ui, "Start/stop slideshow", &mut self.key_binds.toggle_slideshow, default_key_binds.toggle_slideshow);
+            key_bind_widget(ui, "Pause slideshow", &mut self.key_binds.pause_slideshow, default_key_binds.pause_slideshow);
             if self.experimental_features {
                 key_bind_widget(ui, "Pick color", &mut self.key_binds.pick_color, default_key_binds.pick_color);
             }
@@ -137,5 +242,6 @@ impl Settings {
 pub enum SettingsUpdate {
     ReloadTheme,
     ReloadTexture,
-    CloseColorAnalyzer
+    CloseColorAnalyzer,
+    ResizeCache
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::{collections::HashSet, path::PathBuf, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_directory::ImageDirectory;
+
+const CROSSFADE_DURATION: Duration = Duration::from_millis(600);
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Transition {
+    #[default]
+    Cut,
+    Crossfade
+}
+
+/// How the slideshow goes through the images, changed in the settings.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlideshowOptions {
+    // in seconds
+    pub interval: f32,
+    pub shuffle: bool,
+    // start over when every image was shown, otherwise the slideshow stops
+    pub repeat: bool,
+    pub transition: Transition,
+    pub pause_on_hover: bool
+}
+
+impl Default for SlideshowOptions {
+    fn default() -> Self {
+        Self { interval: 5.0, shuffle: false, repeat: true, transition: Transition::Cut, pause_on_hover: false }
+    }
+}
+
+/// Goes through the images on a timer.
+pub struct Slideshow {
+    options: SlideshowOptions,
+    last_change: Instant,
+    pub paused: bool,
+    // the images that were shown since the slideshow started or started over
+    shown: HashSet<PathBuf>,
+    // when the current image started to fade in, it starts on the first frame it's shown
+    fade_start: Option<Instant>,
+    random: u64
+}
+
+impl Slideshow {
+    pub fn new(options: SlideshowOptions) -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        Self { options, last_change: Instant::now(), paused: false, shown: HashSet::new(), fade_start: None, random: seed | 1 }
+    }
+
+    pub fn transition(&self) -> Transition {
+        self.options.transition
+    }
+
+    /// Returns true when it's time for the next image, a repaint is requested for when that happens.
+    /// The time starts over while it's paused or the pointer is over the image.
+    pub fn update(&mut self, ctx: &egui::Context, hovered: bool) -> bool {
+        if self.paused || (hovered && self.options.pause_on_hover) {
+            self.last_change = Instant::now();
+            return false;
+        }
+        let interval = Duration::from_secs_f32(self.options.interval.max(0.1));
+        let elapsed = self.last_change.elapsed();
+        if elapsed >= interval {
+            self.last_change = Instant::now();
+            ctx.request_repaint_after(interval);
+            true
+        }
+        else {
+            ctx.request_repaint_after(interval - elapsed);
+            false
+        }
+    }
+
+    /// Moves the directory to the next image, `None` when every image was shown and it doesn't repeat.
+    pub fn next(&mut self, directory: &mut ImageDirectory) -> Option<PathBuf> {
+        self.shown.insert(directory.current_image_path().clone());
+        if directory.filtered_images().all(|(_, path)| self.shown.contains(path)) {
+            if !self.options.repeat {
+                return None;
+            }
+            self.shown.clear();
+            self.shown.insert(directory.current_image_path().clone());
+        }
+        self.fade_start = None;
+        if !self.options.shuffle {
+            return Some(directory.next_image().clone());
+        }
+        let remaining: Vec<usize> = directory.filtered_images()
+            .filter(|(_, path)| !self.shown.contains(*path))
+            .map(|(index, _)| index)
+            .collect();
+        // a single image is shown again
+        let index = remaining.get(self.random_below(remaining.len())).copied().unwrap_or(directory.image_index());
+        Some(directory.go_to(index).clone())
+    }
+
+    /// The opacity of the image that fades in, it's fully visible once the crossfade is done.
+    pub fn fade_in(&mut self, ctx: &egui::Context) -> f32 {
+        let start = *self.fade_start.get_or_insert_with(Instant::now);
+        let progress = start.elapsed().as_secs_f32() / CROSSFADE_DURATION.as_secs_f32();
+        if progress < 1.0 {
+            ctx.request_repaint();
+        }
+        progress.min(1.0)
+    }
+
+    // xorshift, the order only has to look random
+    fn random_below(&mut self, count: usize) -> usize {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        if count == 0 { 0 } else { (self.random % count as u64) as usize }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort_order::SortOrder;
+
+    #[test]
+    fn shows_every_image_once() {
+        let directory_path = std::env::temp_dir().join(format!("enchanted-view-slideshow-{}", std::process::id()));
+        std::fs::create_dir_all(&directory_path).unwrap();
+        for name in ["a.png", "b.png", "c.png", "d.png"] {
+            std::fs::write(directory_path.join(name), b"").unwrap();
+        }
+        let mut directory = ImageDirectory::from_paths(std::slice::from_ref(&directory_path), SortOrder::default(), false).unwrap().unwrap();
+
+        let mut slideshow = Slideshow::new(SlideshowOptions { shuffle: true, repeat: false, ..Default::default() });
+        let mut shown = HashSet::from([directory.current_image_path().clone()]);
+        while let Some(path) = slideshow.next(&mut directory) {
+            assert!(shown.insert(path));
+        }
+        assert_eq!(shown.len(), 4);
+
+        let mut slideshow = Slideshow::new(SlideshowOptions::default());
+        for _ in 0..10 {
+            assert!(slideshow.next(&mut directory).is_some());
+        }
+        std::fs::remove_dir_all(&directory_path).unwrap();
+    }
+
+    #[test]
+    fn repeats_a_single_image() {
+        let directory_path = std::env::temp_dir().join(format!("enchanted-view-slideshow-single-{}", std::process::id()));
+        std::fs::create_dir_all(&directory_path).unwrap();
+        std::fs::write(directory_path.join("a.png"), b"").unwrap();
+        let mut directory = ImageDirectory::from_paths(std::slice::from_ref(&directory_path), SortOrder::default(), false).unwrap().unwrap();
+
+        let mut slideshow = Slideshow::new(SlideshowOptions { shuffle: true, ..Default::default() });
+        for _ in 0..3 {
+            assert_eq!(slideshow.next(&mut directory), Some(directory_path.join("a.png")));
+        }
+        std::fs::remove_dir_all(&directory_path).unwrap();
+    }
+}
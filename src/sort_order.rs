@@ -0,0 +1,268 @@
+use std::{cmp::Ordering, collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_format::FileFormat;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Modified,
+    Created,
+    Size,
+    Format,
+    Dimensions
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 6] = [SortKey::Name, SortKey::Modified, SortKey::Created, SortKey::Size, SortKey::Format, SortKey::Dimensions];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Modified => "Date modified",
+            SortKey::Created => "Date created",
+            SortKey::Size => "File size",
+            SortKey::Format => "Format",
+            SortKey::Dimensions => "Dimensions"
+        }
+    }
+
+    // the name and the format come from the path
+    fn reads_files(&self) -> bool {
+        !matches!(self, SortKey::Name | SortKey::Format)
+    }
+}
+
+/// The order the images of a directory are browsed in.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool
+}
+
+impl SortOrder {
+    /// The names are compared by the path from the root they're in, so the images of a subfolder stay together.
+    /// Images whose values weren't read yet are sorted by their name before the others.
+    pub fn sort(&self, paths: &mut [PathBuf], roots: &[PathBuf], values: &SortValues) {
+        paths.sort_by_cached_key(|path| SortEntry::new(self.key, path, roots, values));
+        if self.descending {
+            paths.reverse();
+        }
+    }
+
+    /// Where `path` should be inserted to keep the sorted `paths` sorted.
+    pub fn position(&self, paths: &[PathBuf], path: &Path, roots: &[PathBuf], values: &SortValues) -> usize {
+        let entry = SortEntry::new(self.key, path, roots, values);
+        paths.partition_point(|other| {
+            let ordering = SortEntry::new(self.key, other, roots, values).cmp(&entry);
+            if self.descending { ordering.is_gt() } else { ordering.is_lt() }
+        })
+    }
+}
+
+// files that can't be read are sorted first, the name decides between equal values
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SortEntry {
+    value: SortValue,
+    // the folders and file name below the root
+    name: Vec<NaturalName>
+}
+
+/// The values the images are sorted by, reading them from the files is slow so they are kept until a file changes.
+#[derive(Default)]
+pub struct SortValues {
+    key: SortKey,
+    values: HashMap<PathBuf, SortValue>,
+    // the paths whose values are read on a worker
+    requested: HashSet<PathBuf>
+}
+
+impl SortValues {
+    /// Reads the values of `paths` from the files, it's done on worker threads.
+    pub fn read(key: SortKey, paths: &[PathBuf]) -> Self {
+        let paths = paths.iter().filter(|_| key.reads_files());
+        Self { key, values: paths.map(|path| (path.clone(), SortValue::read(key, path))).collect(), requested: HashSet::new() }
+    }
+
+    /// The paths whose values weren't read or requested yet, they're requested now and should be read with `read`.
+    /// The values of the previous key are dropped.
+    pub fn request(&mut self, key: SortKey, paths: &[PathBuf]) -> Vec<PathBuf> {
+        self.set_key(key);
+        if !key.reads_files() {
+            return Vec::new();
+        }
+        let missing: Vec<PathBuf> = paths.iter()
+            .filter(|path| !self.values.contains_key(*path) && !self.requested.contains(*path))
+            .cloned()
+            .collect();
+        self.requested.extend(missing.iter().cloned());
+        missing
+    }
+
+    /// Adds the values that were read on a worker, returns false if they're for another key.
+    pub fn extend(&mut self, other: SortValues) -> bool {
+        if other.key != self.key {
+            return false;
+        }
+        for path in other.values.keys() {
+            self.requested.remove(path);
+        }
+        self.values.extend(other.values);
+        true
+    }
+
+    /// The value is requested again the next time, like when the file changed.
+    pub fn remove(&mut self, path: &Path) {
+        self.values.remove(path);
+        self.requested.remove(path);
+    }
+
+    fn set_key(&mut self, key: SortKey) {
+        if key != self.key {
+            self.key = key;
+            self.values.clear();
+            self.requested.clear();
+        }
+    }
+
+    // the files are never read here, it's too slow for the ui thread
+    fn get(&self, key: SortKey, path: &Path) -> SortValue {
+        if !key.reads_files() {
+            return SortValue::read(key, path);
+        }
+        self.values.get(path).filter(|_| self.key == key).cloned().unwrap_or(SortValue::Name)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Name,
+    Time(Option<SystemTime>),
+    Size(Option<u64>),
+    Format(String),
+    // the pixel count
+    Dimensions(Option<u64>)
+}
+
+impl SortValue {
+    fn read(key: SortKey, path: &Path) -> Self {
+        match key {
+            SortKey::Name => SortValue::Name,
+            SortKey::Modified => SortValue::Time(fs::metadata(path).and_then(|metadata| metadata.modified()).ok()),
+            SortKey::Created => SortValue::Time(fs::metadata(path).and_then(|metadata| metadata.created()).ok()),
+            SortKey::Size => SortValue::Size(fs::metadata(path).map(|metadata| metadata.len()).ok()),
+            SortKey::Format => SortValue::Format(FileFormat::from_path(path).map(|format| format.name()).unwrap_or_default()),
+            // only the header is read
+            SortKey::Dimensions => SortValue::Dimensions(image::image_dimensions(path).ok().map(|(width, height)| width as u64 * height as u64))
+        }
+    }
+}
+
+impl SortEntry {
+    fn new(key: SortKey, path: &Path, roots: &[PathBuf], values: &SortValues) -> Self {
+        let value = values.get(key, path);
+        let relative = roots.iter().find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or_else(|| path.file_name().map_or(path, Path::new));
+        let name = relative.iter().map(|part| NaturalName(part.to_string_lossy().to_string())).collect();
+        Self { value, name }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+struct NaturalName(String);
+
+impl PartialOrd for NaturalName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NaturalName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.0, &other.0)
+    }
+}
+
+/// Compares the numbers in the names by their value so "image2" comes before "image10", letters are compared without case.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_number(&mut a_chars);
+                let b_number = take_number(&mut b_chars);
+                // without the leading zeros a longer number is a bigger number
+                a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(&b_number))
+            },
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+                a_chars.next();
+                b_chars.next();
+                ordering
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    // names that only differ by case or leading zeros still need a stable order
+    a.cmp(b)
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(char) = chars.next_if(|char| char.is_ascii_digit()) {
+        if !(number.is_empty() && char == '0') {
+            number.push(char);
+        }
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["img10.png", "IMG2.png", "img1.png", "img02.png", "a.png"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["a.png", "img1.png", "IMG2.png", "img02.png", "img10.png"]);
+
+        let order = SortOrder { key: SortKey::Name, descending: true };
+        let paths: Vec<PathBuf> = ["c3.png", "c2.png", "c1.png"].iter().map(PathBuf::from).collect();
+        let values = &SortValues::default();
+        assert_eq!(order.position(&paths, Path::new("c10.png"), &[], values), 0);
+        assert_eq!(order.position(&paths, Path::new("c1.jpg"), &[], values), 3);
+
+        let roots = [PathBuf::from("/images")];
+        let mut paths: Vec<PathBuf> = ["/images/b/a.png", "/images/a/c.png", "/images/b.png", "/images/a/b.png"].iter().map(PathBuf::from).collect();
+        SortOrder::default().sort(&mut paths, &roots, values);
+        assert_eq!(paths, ["/images/a/b.png", "/images/a/c.png", "/images/b/a.png", "/images/b.png"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn requests_values_once() {
+        let paths: Vec<PathBuf> = ["b.png", "a.png"].iter().map(PathBuf::from).collect();
+        let mut values = SortValues::default();
+        assert!(values.request(SortKey::Format, &paths).is_empty());
+        assert_eq!(values.request(SortKey::Size, &paths), paths);
+        assert!(values.request(SortKey::Size, &paths).is_empty());
+        // the unread values are sorted by the name until they're there
+        let mut sorted = paths.clone();
+        SortOrder { key: SortKey::Size, descending: false }.sort(&mut sorted, &[], &values);
+        assert_eq!(sorted, [PathBuf::from("a.png"), PathBuf::from("b.png")]);
+        values.remove(Path::new("a.png"));
+        assert_eq!(values.request(SortKey::Size, &paths), [PathBuf::from("a.png")]);
+        // the values of the old key are dropped
+        assert!(!values.extend(SortValues::read(SortKey::Modified, &paths)));
+        assert!(values.extend(SortValues::read(SortKey::Size, &paths)));
+        assert!(values.request(SortKey::Size, &paths).is_empty());
+    }
+}
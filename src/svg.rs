@@ -0,0 +1,280 @@
+use std::{borrow::Cow, sync::{Arc, OnceLock, mpsc::{self, Receiver}}, time::{Duration, Instant}};
+
+use image::{DynamicImage, ImageError, ImageResult, RgbaImage, error::{DecodingError, ImageFormatHint}};
+use resvg::{usvg, usvg::roxmltree, tiny_skia};
+
+// the side of the image that is decoded with the document, bigger documents are scaled down to it
+const MAX_BASE_SIDE: f32 = 8192.0;
+// waiting for the zoom to settle so scrolling doesn't start a render every frame
+const RENDER_DELAY: Duration = Duration::from_millis(150);
+// small zoom changes aren't noticeable, rendering again for them is a waste
+const RENDER_SCALE_TOLERANCE: f32 = 0.05;
+// given to the elements without an id, usvg keeps the ids so its nodes can be found for the source elements
+const GENERATED_ID_PREFIX: &str = "enchanted-view-element-";
+
+/// An element of the source document, usvg turns shapes into paths and drops most groups.
+pub struct SvgElement {
+    pub label: String,
+    // in document units, elements that aren't rendered have none
+    pub bounds: Option<egui::Rect>,
+    pub children: Vec<SvgElement>
+}
+
+impl SvgElement {
+    fn from_node(node: roxmltree::Node, tree: &usvg::Tree) -> Self {
+        let children: Vec<SvgElement> = node.children()
+            .filter(|child| child.is_element())
+            .map(|child| SvgElement::from_node(child, tree))
+            .collect();
+        let id = node.attribute("id").unwrap_or_default();
+        let bounds = tree.node_by_id(id)
+            .map(|rendered| to_egui_rect(rendered.abs_bounding_box()))
+            // a text's spans are part of the text node
+            .or_else(|| children.iter().filter_map(|child| child.bounds).reduce(egui::Rect::union));
+        let id = if id.starts_with(GENERATED_ID_PREFIX) { "" } else { id };
+        Self {
+            label: element_label(node.tag_name().name(), id),
+            bounds,
+            children
+        }
+    }
+}
+
+pub struct SvgDocument {
+    tree: usvg::Tree,
+    root: SvgElement
+}
+
+impl SvgDocument {
+    pub fn parse(bytes: &[u8]) -> ImageResult<Self> {
+        let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+            Cow::Owned(usvg::decompress_svgz(bytes).map_err(decoding_error)?)
+        }
+        else {
+            Cow::Borrowed(bytes)
+        };
+        let text = std::str::from_utf8(&bytes).map_err(decoding_error)?;
+        let text = with_element_ids(text).map_err(decoding_error)?;
+        let xml = roxmltree::Document::parse_with_options(&text, xml_options()).map_err(decoding_error)?;
+        let tree = usvg::Tree::from_xmltree(&xml, &options()).map_err(decoding_error)?;
+        let mut root = SvgElement::from_node(xml.root_element(), &tree);
+        root.bounds = Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(tree.size().width(), tree.size().height())));
+        Ok(Self { tree, root })
+    }
+
+    pub fn size(&self) -> egui::Vec2 {
+        egui::vec2(self.tree.size().width(), self.tree.size().height())
+    }
+
+    pub fn root(&self) -> &SvgElement {
+        &self.root
+    }
+
+    /// The scale of the image decoded with the document, it's 1 unless the document is huge.
+    pub fn base_scale(&self) -> f32 {
+        self.max_scale(MAX_BASE_SIDE).min(1.0)
+    }
+
+    /// The biggest scale that fits in a `max_side` sized image.
+    pub fn max_scale(&self, max_side: f32) -> f32 {
+        max_side / self.size().max_elem().max(1.0)
+    }
+
+    pub fn render_image(&self, scale: f32) -> ImageResult<DynamicImage> {
+        let pixmap = self.render(scale).ok_or_else(|| {
+            ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("SVG")), "The document has no size."))
+        })?;
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let pixels = pixmap.pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        let image = RgbaImage::from_raw(width, height, pixels).expect("The buffer matches the pixmap size.");
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+
+    fn render_color_image(&self, scale: f32) -> Option<egui::ColorImage> {
+        let pixmap = self.render(scale)?;
+        Some(egui::ColorImage::from_rgba_premultiplied([pixmap.width() as usize, pixmap.height() as usize], pixmap.data()))
+    }
+
+    fn render(&self, scale: f32) -> Option<tiny_skia::Pixmap> {
+        let size = (self.size() * scale).ceil().max(egui::Vec2::splat(1.0));
+        let mut pixmap = tiny_skia::Pixmap::new(size.x as u32, size.y as u32)?;
+        resvg::render(&self.tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+        Some(pixmap)
+    }
+}
+
+/// Renders the document again whenever the zoom changes so it stays sharp.
+pub struct SvgView {
+    document: Arc<SvgDocument>,
+    // the scale of the shown texture relative to the document size
+    rendered_scale: f32,
+    requested_scale: f32,
+    requested_at: Instant,
+    render: Option<Receiver<egui::ColorImage>>,
+    // the bounds of the element hovered in the inspector, in document units
+    pub highlighted: Option<egui::Rect>
+}
+
+impl SvgView {
+    pub fn new(document: Arc<SvgDocument>) -> Self {
+        let base_scale = document.base_scale();
+        Self {
+            document,
+            rendered_scale: base_scale,
+            requested_scale: base_scale,
+            requested_at: Instant::now(),
+            render: None,
+            highlighted: None
+        }
+    }
+
+    pub fn document(&self) -> &SvgDocument {
+        &self.document
+    }
+
+    /// The texture was replaced with the base image, it needs to be rendered again.
+    pub fn invalidate(&mut self) {
+        self.rendered_scale = self.document.base_scale();
+        self.render = None;
+    }
+
+    /// `scale` is the screen pixels per document unit.
+    /// Returns the rendered image once a render finished.
+    pub fn update(&mut self, context: &egui::Context, scale: f32) -> Option<egui::ColorImage> {
+        if let Some(image) = self.render.as_ref().and_then(|receiver| receiver.try_recv().ok()) {
+            self.render = None;
+            return Some(image);
+        }
+        let max_side = context.input(|input| input.max_texture_side) as f32;
+        let scale = scale.min(self.document.max_scale(max_side));
+        if (scale - self.requested_scale).abs() > f32::EPSILON {
+            self.requested_scale = scale;
+            self.requested_at = Instant::now();
+        }
+        if (self.requested_scale / self.rendered_scale - 1.0).abs() > RENDER_SCALE_TOLERANCE {
+            let waited = self.requested_at.elapsed();
+            if waited >= RENDER_DELAY {
+                self.start_render(context);
+            }
+            else {
+                context.request_repaint_after(RENDER_DELAY - waited);
+            }
+        }
+        None
+    }
+
+    fn start_render(&mut self, context: &egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        let document = self.document.clone();
+        let context = context.clone();
+        let scale = self.requested_scale;
+        std::thread::spawn(move || {
+            if let Some(image) = document.render_color_image(scale) {
+                // the receiver is gone if the zoom changed again or the image was closed
+                if sender.send(image).is_ok() {
+                    context.request_repaint();
+                }
+            }
+        });
+        // replacing the receiver drops the result of an older render
+        self.render = Some(receiver);
+        self.rendered_scale = scale;
+    }
+}
+
+fn options() -> usvg::Options<'static> {
+    // loading the system fonts is slow, it's done once and shared between all documents
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    let fontdb = FONTS.get_or_init(|| {
+        let mut fonts = usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        Arc::new(fonts)
+    }).clone();
+    usvg::Options { fontdb, ..Default::default() }
+}
+
+fn xml_options() -> roxmltree::ParsingOptions {
+    // the same as usvg
+    roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() }
+}
+
+/// Adds a generated id to the start tags of the elements that don't have one.
+fn with_element_ids(text: &str) -> Result<String, roxmltree::Error> {
+    let xml = roxmltree::Document::parse_with_options(text, xml_options())?;
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (index, node) in xml.descendants().filter(|node| node.is_element()).enumerate() {
+        if node.attribute("id").is_some() {
+            continue;
+        }
+        // elements from entities don't start in the text where their range says
+        let start = node.range().start;
+        let Some(tag) = text.get(start..).and_then(|tag| tag.strip_prefix('<')) else { continue };
+        let name_end = start + 1 + tag.find(|char: char| char.is_whitespace() || char == '/' || char == '>').unwrap_or(tag.len());
+        if start < copied || !text[start + 1..name_end].ends_with(node.tag_name().name()) {
+            continue;
+        }
+        result.push_str(&text[copied..name_end]);
+        result.push_str(&format!(" id=\"{GENERATED_ID_PREFIX}{index}\""));
+        copied = name_end;
+    }
+    result.push_str(&text[copied..]);
+    Ok(result)
+}
+
+fn decoding_error(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("SVG")), error))
+}
+
+fn element_label(kind: &str, id: &str) -> String {
+    if id.is_empty() {
+        kind.to_owned()
+    }
+    else {
+        format!("{kind} #{id}")
+    }
+}
+
+fn to_egui_rect(rect: tiny_skia::Rect) -> egui::Rect {
+    egui::Rect::from_min_max(egui::pos2(rect.left(), rect.top()), egui::pos2(rect.right(), rect.bottom()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+        <g id="layer">
+            <rect id="box" x="2" y="2" width="4" height="4" fill="#ff0000"/>
+            <circle cx="12" cy="4" r="2"/>
+        </g>
+    </svg>"##;
+
+    #[test]
+    fn element_tree() {
+        let document = SvgDocument::parse(DOCUMENT).expect("The document is valid.");
+        assert_eq!(document.size(), egui::vec2(20.0, 10.0));
+        let layer = &document.root().children[0];
+        assert_eq!(layer.label, "g #layer");
+        assert_eq!(layer.children[0].label, "rect #box");
+        assert_eq!(layer.children[0].bounds, Some(egui::Rect::from_min_size(egui::pos2(2.0, 2.0), egui::vec2(4.0, 4.0))));
+        let circle = &layer.children[1];
+        assert_eq!(circle.label, "circle");
+        assert_eq!(circle.bounds, Some(egui::Rect::from_min_size(egui::pos2(10.0, 2.0), egui::vec2(4.0, 4.0))));
+    }
+
+    #[test]
+    fn renders_at_scale() {
+        let document = SvgDocument::parse(DOCUMENT).expect("The document is valid.");
+        let image = document.render_image(2.0).expect("The document has a size.").to_rgba8();
+        assert_eq!(image.dimensions(), (40, 20));
+        assert_eq!(image.get_pixel(8, 8).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(30, 8).0, [0, 0, 0, 0]);
+    }
+}
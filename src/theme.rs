@@ -10,7 +10,7 @@ pub struct ImageButtonStyle {
     pub hover_bg: egui::Color32
 }
 
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
 pub enum ThemeKind {
     Light,
     Dark
@@ -0,0 +1,146 @@
+use std::{fs, io::{self, Cursor, Write}, path::{Path, PathBuf}, time::UNIX_EPOCH};
+
+use image::RgbaImage;
+use md5::{Digest, Md5};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+// the characters glib leaves as they are in file uris, other file managers look for the same hash
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-').remove(b'_').remove(b'.').remove(b'~')
+    .remove(b'!').remove(b'$').remove(b'&').remove(b'\'').remove(b'(').remove(b')')
+    .remove(b'*').remove(b'+').remove(b',').remove(b';').remove(b'=').remove(b':').remove(b'@').remove(b'/');
+
+/// The sizes of the freedesktop thumbnail spec, thumbnails fit in a square of this size.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Normal,
+    Large
+}
+
+impl ThumbnailSize {
+    pub fn pixels(&self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256
+        }
+    }
+
+    fn directory_name(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large"
+        }
+    }
+}
+
+/// Thumbnails shared with file managers and other viewers in `~/.cache/thumbnails`.
+/// A thumbnail is only used if it was made from the current version of the file.
+pub struct ThumbnailCache {
+    directory: PathBuf
+}
+
+impl ThumbnailCache {
+    /// `None` when there is no cache directory, like on windows.
+    pub fn new(size: ThumbnailSize) -> Option<Self> {
+        let cache = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(Self { directory: cache.join("thumbnails").join(size.directory_name()) })
+    }
+
+    pub fn load(&self, path: &Path) -> Option<RgbaImage> {
+        let uri = file_uri(path)?;
+        let bytes = fs::read(self.thumbnail_path(&uri)).ok()?;
+        let decoder = png::Decoder::new(Cursor::new(&bytes));
+        let reader = decoder.read_info().ok()?;
+        let text = &reader.info().uncompressed_latin1_text;
+        let value = |keyword: &str| text.iter().find(|chunk| chunk.keyword == keyword).map(|chunk| chunk.text.as_str());
+        // the file changed since the thumbnail was made
+        if value("Thumb::URI") != Some(uri.as_str()) || value("Thumb::MTime") != modified_seconds(path).map(|seconds| seconds.to_string()).as_deref() {
+            return None;
+        }
+        image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).ok().map(|image| image.into_rgba8())
+    }
+
+    pub fn store(&self, path: &Path, thumbnail: &RgbaImage) -> io::Result<()> {
+        let (Some(uri), Some(modified)) = (file_uri(path), modified_seconds(path)) else {
+            return Ok(());
+        };
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, thumbnail.width(), thumbnail.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk(String::from("Thumb::URI"), uri.clone()).map_err(io::Error::other)?;
+        encoder.add_text_chunk(String::from("Thumb::MTime"), modified.to_string()).map_err(io::Error::other)?;
+        encoder.add_text_chunk(String::from("Software"), String::from("Enchanted View")).map_err(io::Error::other)?;
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(thumbnail.as_raw()))
+            .map_err(io::Error::other)?;
+        create_private_dir(&self.directory)?;
+        // other programs might read the thumbnail while it's written, so it's renamed into place when it's done
+        let target = self.thumbnail_path(&uri);
+        let temporary = target.with_extension(format!("{}.tmp", std::process::id()));
+        let mut file = create_private_file(&temporary)?;
+        file.write_all(&png)?;
+        fs::rename(&temporary, &target)
+    }
+
+    fn thumbnail_path(&self, uri: &str) -> PathBuf {
+        let hash = Md5::digest(uri.as_bytes());
+        let name: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.directory.join(format!("{name}.png"))
+    }
+}
+
+fn file_uri(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\\', "/").into_bytes();
+    Some(format!("file://{}", percent_encoding::percent_encode(&bytes, URI_PATH)))
+}
+
+fn modified_seconds(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+}
+
+// the spec asks for the thumbnails to be private, they show what's in the user's files
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(path)
+}
+
+fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_thumbnails() {
+        let directory = std::env::temp_dir().join(format!("enchanted-view-thumbnails-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let image_path = directory.join("a photo.png");
+        fs::write(&image_path, b"not really a png").unwrap();
+        let cache = ThumbnailCache { directory: directory.join("normal") };
+        assert!(cache.load(&image_path).is_none());
+
+        let thumbnail = RgbaImage::from_pixel(4, 2, image::Rgba([10, 20, 30, 255]));
+        cache.store(&image_path, &thumbnail).unwrap();
+        assert_eq!(cache.load(&image_path), Some(thumbnail));
+        assert!(file_uri(&image_path).unwrap().ends_with("/a%20photo.png"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
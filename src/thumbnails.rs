@@ -0,0 +1,199 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::{mpsc::{self, Receiver}, Arc, Condvar, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use image::RgbaImage;
+
+use crate::{image_loader::decode_file, thumbnail_cache::{ThumbnailCache, ThumbnailSize}};
+
+enum Thumbnail {
+    Loading,
+    Loaded(egui::TextureHandle),
+    // the file couldn't be decoded, it isn't tried again
+    Failed
+}
+
+// the textures of the tiles that were off screen the longest are freed above this
+const MAX_THUMBNAILS: usize = 500;
+// how often the files of the shown thumbnails are checked for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Entry {
+    // the modification time of the file when it was requested, a newer file is loaded again
+    modified: Option<SystemTime>,
+    thumbnail: Thumbnail,
+    // the frame it was last shown in
+    last_used: u64,
+    // when the modification time was read
+    checked: Instant
+}
+
+// the requests that weren't started yet
+#[derive(Default)]
+struct Queue {
+    paths: Vec<PathBuf>,
+    closed: bool
+}
+
+/// Small textures of image files, they are decoded on worker threads and kept in the freedesktop thumbnail cache.
+pub struct Thumbnails {
+    context: egui::Context,
+    thumbnails: HashMap<PathBuf, Entry>,
+    // the frame the stale requests and textures were last dropped in
+    frame: u64,
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    receiver: Receiver<(PathBuf, Option<egui::ColorImage>)>
+}
+
+impl Thumbnails {
+    pub fn new(context: &egui::Context, size: ThumbnailSize) -> Self {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (results, receiver) = mpsc::channel();
+        let cache = ThumbnailCache::new(size).map(Arc::new);
+        // a few cores are left for the ui and the image loader
+        let workers = std::thread::available_parallelism().map_or(1, |count| count.get() / 2).clamp(1, 4);
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let results = results.clone();
+            let context = context.clone();
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                while let Some(path) = next_request(&queue) {
+                    let thumbnail = load_thumbnail(&path, size, cache.as_deref()).map(|thumbnail| {
+                        let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+                        egui::ColorImage::from_rgba_unmultiplied(size, thumbnail.as_raw())
+                    });
+                    if results.send((path, thumbnail)).is_err() {
+                        break;
+                    }
+                    context.request_repaint();
+                }
+            });
+        }
+        Self { context: context.clone(), thumbnails: HashMap::new(), frame: 0, queue, receiver }
+    }
+
+    /// Returns the thumbnail if it's ready, otherwise it's queued for loading.
+    pub fn get(&mut self, path: &Path) -> Option<&egui::TextureHandle> {
+        self.start_frame();
+        self.receive();
+        let frame = self.frame;
+        // reading the modification time of every tile in every frame adds up, the files rarely change
+        let checked = self.thumbnails.get(path).is_some_and(|entry| entry.checked.elapsed() < CHECK_INTERVAL);
+        if !checked {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            match self.thumbnails.get_mut(path) {
+                Some(entry) if entry.modified == modified => entry.checked = Instant::now(),
+                _ => {
+                    let (queue, condvar) = &*self.queue;
+                    queue.lock().expect("A thumbnail worker panicked.").paths.push(path.to_path_buf());
+                    condvar.notify_one();
+                    self.thumbnails.insert(path.to_path_buf(), Entry { modified, thumbnail: Thumbnail::Loading, last_used: frame, checked: Instant::now() });
+                }
+            }
+        }
+        let entry = self.thumbnails.get_mut(path).expect("The thumbnail was inserted above.");
+        entry.last_used = frame;
+        match &entry.thumbnail {
+            Thumbnail::Loaded(texture) => Some(texture),
+            _ => None
+        }
+    }
+
+    // once per frame, drops what wasn't shown in the last frame
+    fn start_frame(&mut self) {
+        let frame = self.context.frame_nr();
+        if frame == self.frame {
+            return;
+        }
+        self.frame = frame;
+        let shown = |entry: &Entry| entry.last_used + 1 >= frame;
+        let thumbnails = &mut self.thumbnails;
+        // requests of tiles that were scrolled away are dropped, they are queued again when they come back
+        if let Ok(mut queue) = self.queue.0.lock() {
+            queue.paths.retain(|path| {
+                let keep = thumbnails.get(path).is_some_and(shown);
+                if !keep {
+                    thumbnails.remove(path);
+                }
+                keep
+            });
+        }
+        if thumbnails.len() > MAX_THUMBNAILS {
+            // the ones that are still loading are skipped, their results would be thrown away
+            let mut off_screen: Vec<(u64, PathBuf)> = thumbnails.iter()
+                .filter(|(_, entry)| !shown(entry) && !matches!(entry.thumbnail, Thumbnail::Loading))
+                .map(|(path, entry)| (entry.last_used, path.clone()))
+                .collect();
+            off_screen.sort_unstable_by_key(|(last_used, _)| *last_used);
+            let excess = thumbnails.len() - MAX_THUMBNAILS;
+            for (_, path) in off_screen.into_iter().take(excess) {
+                thumbnails.remove(&path);
+            }
+        }
+    }
+
+    /// Paints the thumbnail in the middle of `rect`, nothing is painted while it's loading.
+    pub fn paint(&mut self, painter: &egui::Painter, path: &Path, rect: egui::Rect) {
+        if let Some(texture) = self.get(path) {
+            let size = texture.size_vec2() * (rect.size() / texture.size_vec2()).min_elem();
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            painter.image(texture.id(), egui::Rect::from_center_size(rect.center(), size), uv, egui::Color32::WHITE);
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Ok((path, image)) = self.receiver.try_recv() {
+            // it's requested again if the file changed in the meantime
+            let Some(entry) = self.thumbnails.get_mut(&path) else { continue };
+            entry.thumbnail = match image {
+                Some(image) => Thumbnail::Loaded(self.context.load_texture(format!("thumbnail {}", path.display()), image, egui::TextureOptions::LINEAR)),
+                None => Thumbnail::Failed
+            };
+        }
+    }
+}
+
+impl Drop for Thumbnails {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+        if let Ok(mut queue) = queue.lock() {
+            queue.closed = true;
+        }
+        condvar.notify_all();
+    }
+}
+
+// the newest request is taken first since it's the one that's on screen, `None` once the thumbnails are dropped
+fn next_request(queue: &(Mutex<Queue>, Condvar)) -> Option<PathBuf> {
+    let (queue, condvar) = queue;
+    let mut queue = queue.lock().ok()?;
+    loop {
+        if queue.closed {
+            return None;
+        }
+        if let Some(path) = queue.paths.pop() {
+            return Some(path);
+        }
+        queue = condvar.wait(queue).ok()?;
+    }
+}
+
+fn load_thumbnail(path: &Path, size: ThumbnailSize, cache: Option<&ThumbnailCache>) -> Option<RgbaImage> {
+    if let Some(thumbnail) = cache.and_then(|cache| cache.load(path)) {
+        return Some(thumbnail);
+    }
+    let decoded = decode_file(path).ok()?;
+    let pixels = size.pixels();
+    // small images aren't scaled up
+    let mut thumbnail = if decoded.image.width() > pixels || decoded.image.height() > pixels {
+        decoded.image.thumbnail(pixels, pixels).to_rgba8()
+    } else { decoded.image.to_rgba8() };
+    if let Some(profile) = decoded.color_profile.as_ref() {
+        profile.to_display(&mut thumbnail);
+    }
+    if let Some(cache) = cache {
+        if let Err(error) = cache.store(path, &thumbnail) {
+            eprintln!("Couldn't store the thumbnail of '{}': {error}", path.display());
+        }
+    }
+    Some(thumbnail)
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use egui::{ColorImage, TextureHandle, TextureOptions};
+
+#[derive(Clone)]
+pub struct TextureTile {
+    pub handle: TextureHandle,
+    // the part of the image this tile covers, from 0 to 1
+    pub uv: egui::Rect
+}
+
+/// A texture split into tiles so images bigger than the max texture side of the gpu can be shown.
+#[derive(Clone)]
+pub struct TiledTexture {
+    name: String,
+    tiles: Vec<TextureTile>
+}
+
+/// The tiles of an image before they are uploaded, splitting doesn't need the ui thread so it's done by the loader.
+#[derive(Clone)]
+pub struct TiledImage {
+    size: [usize; 2],
+    // shared with the textures, uploading them doesn't copy the pixels
+    tiles: Vec<(Arc<ColorImage>, egui::Rect)>
+}
+
+impl TiledImage {
+    pub fn new(image: ColorImage, max_side: usize) -> Self {
+        let size = image.size;
+        let tiles = split_image(image, max_side).into_iter().map(|(image, uv)| (Arc::new(image), uv)).collect();
+        Self { size, tiles }
+    }
+
+    pub fn size(&self) -> egui::Vec2 {
+        egui::vec2(self.size[0] as f32, self.size[1] as f32)
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.size[0] * self.size[1] * 4
+    }
+}
+
+impl TiledTexture {
+    pub fn load(context: &egui::Context, image: ColorImage, options: TextureOptions, name: impl Into<String>) -> Self {
+        let max_side = context.input(|input| input.max_texture_side);
+        Self::upload(context, &TiledImage::new(image, max_side), options, name)
+    }
+
+    pub fn upload(context: &egui::Context, image: &TiledImage, options: TextureOptions, name: impl Into<String>) -> Self {
+        let name = name.into();
+        let count = image.tiles.len();
+        let tiles = image.tiles.iter()
+            .enumerate()
+            .map(|(index, (image, uv))| {
+                // keeping the name of a single texture as is makes the common case easier to debug
+                let tile_name = if count == 1 { name.clone() } else { format!("{name}@{index}") };
+                TextureTile { handle: context.load_texture(tile_name, image.clone(), options), uv: *uv }
+            })
+            .collect();
+        Self { name, tiles }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tiles(&self) -> &[TextureTile] {
+        &self.tiles
+    }
+}
+
+/// Splits the image into tiles that are at most `max_side` wide and tall, with the uv rect each tile covers.
+fn split_image(image: ColorImage, max_side: usize) -> Vec<(ColorImage, egui::Rect)> {
+    let [width, height] = image.size;
+    if width <= max_side && height <= max_side {
+        return vec![(image, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)))];
+    }
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(max_side) {
+        for x in (0..width).step_by(max_side) {
+            let size = [max_side.min(width - x), max_side.min(height - y)];
+            let mut pixels = Vec::with_capacity(size[0] * size[1]);
+            for row in y..y + size[1] {
+                let start = row * width + x;
+                pixels.extend_from_slice(&image.pixels[start..start + size[0]]);
+            }
+            let uv = egui::Rect::from_min_max(
+                egui::pos2(x as f32 / width as f32, y as f32 / height as f32),
+                egui::pos2((x + size[0]) as f32 / width as f32, (y + size[1]) as f32 / height as f32)
+            );
+            tiles.push((ColorImage { size, pixels }, uv));
+        }
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_into_tiles() {
+        let pixels = (0..15).map(egui::Color32::from_gray).collect();
+        let image = ColorImage { size: [5, 3], pixels };
+        let tiles = split_image(image, 2);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0].0.size, [2, 2]);
+        assert_eq!(tiles[0].0.pixels, [0, 1, 5, 6].map(egui::Color32::from_gray));
+        // the last column and row are smaller
+        assert_eq!(tiles[5].0.size, [1, 1]);
+        assert_eq!(tiles[5].0.pixels, [egui::Color32::from_gray(14)]);
+        assert_eq!(tiles[5].1, egui::Rect::from_min_max(egui::pos2(0.8, 2.0 / 3.0), egui::pos2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn small_image_is_one_tile() {
+        let image = ColorImage::new([4, 4], egui::Color32::WHITE);
+        let tiles = split_image(image, 4);
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(tiles[0].1, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)));
+    }
+}
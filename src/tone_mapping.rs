@@ -0,0 +1,97 @@
+use image::{DynamicImage, GenericImageView, Rgba32FImage, RgbaImage};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToneMapOperator {
+    Clamp,
+    Reinhard,
+    AcesFilmic
+}
+
+impl ToneMapOperator {
+    pub fn iterator() -> impl Iterator<Item = ToneMapOperator> {
+        [ToneMapOperator::Clamp, ToneMapOperator::Reinhard, ToneMapOperator::AcesFilmic].into_iter()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMapOperator::Clamp => "Clamp",
+            ToneMapOperator::Reinhard => "Reinhard",
+            ToneMapOperator::AcesFilmic => "ACES filmic"
+        }
+    }
+
+    fn map(&self, value: f32) -> f32 {
+        match self {
+            ToneMapOperator::Clamp => value,
+            ToneMapOperator::Reinhard => value / (1.0 + value),
+            // the curve fit by Krzysztof Narkowicz
+            ToneMapOperator::AcesFilmic => (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14)
+        }
+    }
+}
+
+/// How floating point images are brought into the displayable range, the source image is never modified.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ToneMapping {
+    // in stops, every stop doubles the brightness
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+    pub gamma: f32
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self { exposure: 0.0, operator: ToneMapOperator::AcesFilmic, gamma: 2.2 }
+    }
+}
+
+impl ToneMapping {
+    /// Float images hold linear values that can go above 1, they are the only ones that are tone mapped.
+    pub fn applies_to(image: &DynamicImage) -> bool {
+        matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_))
+    }
+
+    pub fn map_color(&self, color: [f32; 4]) -> [u8; 4] {
+        let scale = self.exposure.exp2();
+        let gamma = 1.0 / self.gamma.max(f32::EPSILON);
+        let channel = |value: f32| {
+            let mapped = self.operator.map((value * scale).max(0.0)).clamp(0.0, 1.0);
+            (mapped.powf(gamma) * 255.0).round() as u8
+        };
+        [channel(color[0]), channel(color[1]), channel(color[2]), (color[3].clamp(0.0, 1.0) * 255.0).round() as u8]
+    }
+
+    /// The pixel as it's shown, float pixels are tone mapped and others are converted to 8 bits.
+    pub fn display_pixel(&self, image: &DynamicImage, x: u32, y: u32) -> [u8; 4] {
+        match image {
+            DynamicImage::ImageRgb32F(image) => {
+                let [red, green, blue] = image.get_pixel(x, y).0;
+                self.map_color([red, green, blue, 1.0])
+            },
+            DynamicImage::ImageRgba32F(image) => self.map_color(image.get_pixel(x, y).0),
+            other => other.get_pixel(x, y).0
+        }
+    }
+
+    pub fn apply(&self, image: &Rgba32FImage) -> RgbaImage {
+        RgbaImage::from_fn(image.width(), image.height(), |x, y| image::Rgba(self.map_color(image.get_pixel(x, y).0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_colors() {
+        let clamp = ToneMapping { exposure: 0.0, operator: ToneMapOperator::Clamp, gamma: 1.0 };
+        assert_eq!(clamp.map_color([0.5, 4.0, -1.0, 1.0]), [128, 255, 0, 255]);
+        // one stop up doubles the value
+        assert_eq!(ToneMapping { exposure: 1.0, ..clamp }.map_color([0.25, 0.0, 0.0, 1.0]), [128, 0, 0, 255]);
+        let reinhard = ToneMapping { operator: ToneMapOperator::Reinhard, ..clamp };
+        assert_eq!(reinhard.map_color([1.0, 3.0, 0.0, 0.5]), [128, 191, 0, 128]);
+        // values far above 1 still map below white
+        let filmic = ToneMapping::default();
+        assert!(filmic.map_color([8.0, 8.0, 8.0, 1.0])[0] > 250);
+    }
+}